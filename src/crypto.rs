@@ -1,7 +1,12 @@
+use crate::curves::CurveDomain;
 use crate::ellipticcurve::ECPoint;
-use crate::fpelem::{FpElem, GenericUInt};
-use rand::thread_rng;
-use rand::Rng;
+use crate::fpelem::{
+    count_bits_generic_uint, get_nonzero_generic_uint_below, FpElem, GenericUInt, Pow,
+};
+use crate::hashing::{digest_to_scalar, HashAlgorithm};
+use crate::montgomery::{MontFpElem, MontgomeryParams};
+use crate::rfc6979::DynRfc6979;
+use rand::{thread_rng, CryptoRng, RngCore};
 
 use thiserror::Error;
 
@@ -20,48 +25,180 @@ pub enum KeyChainError {
     // Unknown,
     #[error("no private key available for signing")]
     NoPrivateKey,
+    #[error("public key is the point at infinity")]
+    PublicKeyAtInfinity,
+    #[error("no usable nonce found after {0} attempts")]
+    NonceRetriesExhausted(usize),
     #[error("unknown error")]
     Unknown,
 }
 
-fn count_bits_generic_uint<T: GenericUInt>(x: T) -> u64 {
-    let one = T::from(1);
-    let mut count = 0;
-    let mut upperbound = T::from(0);
-    loop {
-        upperbound = upperbound << one;
-        upperbound = upperbound + one;
-        count = count + 1;
-        if upperbound >= x {
-            return count;
-        }
+// Why verify_sig turned a signature down
+#[derive(Error, Debug, PartialEq, Clone)]
+pub enum VerifyError {
+    #[error("hash or signature values are not reduced mod the group order")]
+    WrongModulus,
+    #[error("r is not in [1, n-1]")]
+    ROutOfRange,
+    #[error("s is not in [1, n-1]")]
+    SOutOfRange,
+    #[error("s is above n/2 and strict verification is on")]
+    HighS,
+    #[error("public key is the point at infinity")]
+    PubKeyAtInfinity,
+    #[error("public key is not on the domain's curve")]
+    PubKeyNotOnCurve,
+    #[error("public key is not in the subgroup generated by the generator")]
+    PubKeyNotInSubgroup,
+    #[error("signature does not match the hash and public key")]
+    Mismatch,
+}
+
+// Why recover_pubkey couldn't produce a public key
+#[derive(Error, Debug, PartialEq, Clone)]
+pub enum RecoveryError {
+    #[error("hash or signature values are not reduced mod the group order")]
+    WrongModulus,
+    #[error("r is not in [1, n-1]")]
+    ROutOfRange,
+    #[error("s is not in [1, n-1]")]
+    SOutOfRange,
+    #[error("recovery id {0} is not one of 0, 1, 2, 3")]
+    InvalidRecoveryId(u8),
+    #[error("no point in the generator's subgroup has x = r for this recovery id")]
+    NoPointForR,
+    #[error("recovered public key is the point at infinity")]
+    PubKeyAtInfinity,
+}
+
+// (public key, digest, signature, recovery id) as handed to KeyChain::verify_batch, the digest
+// taken as in verify_prehash. The recovery id says which point with x = r was R, as in
+// RecoverableSignature - entries without one can't be combined and are checked on their own
+pub type BatchEntry<'a, T> = (
+    ECPoint<FpElem<T>>,
+    &'a [u8],
+    Signature<FpElem<T>>,
+    Option<u8>,
+);
+
+// Which entries of a batch failed, and why
+#[derive(Error, Debug, PartialEq, Clone)]
+#[error("{} of the batch's signatures are invalid", .invalid.len())]
+pub struct BatchVerifyError {
+    // (index into the batch, reason), in index order
+    pub invalid: Vec<(usize, VerifyError)>,
+}
+
+// everything about a signature that can be checked without touching the curve
+fn check_signature_values<T: GenericUInt>(
+    hash: &FpElem<T>,
+    signature: &Signature<FpElem<T>>,
+    n: T,
+) -> Result<(), VerifyError> {
+    let zero = T::from(0);
+    let (r, s) = (signature.targetx, signature.sig);
+    if hash.prime != n || r.prime != n || s.prime != n {
+        return Err(VerifyError::WrongModulus);
+    }
+    // the fields are pub so don't assume they were reduced
+    if r.number == zero || r.number >= n {
+        return Err(VerifyError::ROutOfRange);
     }
+    if s.number == zero || s.number >= n {
+        return Err(VerifyError::SOutOfRange);
+    }
+    Ok(())
 }
 
-// generate n random bits where n is minimal bits to represent "under" (in the canonical binary rep)
-// if number generated is bigger, sack this run and try again - rejection sampling - however 0.5 prob of success each time, range is NON INCLUSIVE
-fn get_generic_uint_below<T: GenericUInt>(under: T) -> T {
-    let bits = count_bits_generic_uint(under - T::from(1));
-    let mut nextout = T::from(0);
-    let mut random_bit: u8;
-    // loop will exit with probability 1
-    loop {
-        // draw n uniform random bits
-        for _ in 0..bits {
-            random_bit = thread_rng().gen_range(0..=1);
-            nextout = (nextout << T::from(1)) + T::from(random_bit);
-        }
-        // if the solution is bigger than our desired bound, sample again
-        if nextout >= under {
-            nextout = T::from(0);
-            continue;
-        }
-        // else pass it back out
-        return nextout;
+// the subgroup check costs a scalar multiplication, unless the cofactor is 1 where it's free
+fn check_pubkey<T: GenericUInt>(
+    pubkey: &ECPoint<FpElem<T>>,
+    domain: &CurveDomain<T>,
+) -> Result<(), VerifyError> {
+    if pubkey.position.is_none() {
+        return Err(VerifyError::PubKeyAtInfinity);
+    }
+    if !pubkey.same_curve(&domain.generator()) || !pubkey.is_on_curve() {
+        return Err(VerifyError::PubKeyNotOnCurve);
+    }
+    if !pubkey.is_in_subgroup(domain.n(), domain.cofactor) {
+        return Err(VerifyError::PubKeyNotInSubgroup);
     }
+    Ok(())
 }
 
-#[derive(Debug)]
+// x of u1 G + u2 Q mod n against r, the part of verification that needs the curve.
+// u1, u2 and the points are all public, so the faster variable time multi_mul is fine here
+fn check_equation<T: GenericUInt>(
+    domain: &CurveDomain<T>,
+    pubkey: &ECPoint<FpElem<T>>,
+    u1: &FpElem<T>,
+    u2: &FpElem<T>,
+    r: &FpElem<T>,
+) -> Result<(), VerifyError> {
+    let n = domain.n();
+    match domain
+        .multi_mul(&[(domain.generator(), u1.number), (*pubkey, u2.number)])
+        .position
+    {
+        Some((x, _)) if FpElem::new(x.number, n) == *r => Ok(()),
+        _ => Err(VerifyError::Mismatch),
+    }
+}
+
+// (Q, u1, u2, R) for an entry verify_batch can fold into a combined check
+type CombinedEntry<T> = (ECPoint<FpElem<T>>, FpElem<T>, FpElem<T>, ECPoint<FpElem<T>>);
+
+// Checks
+//     (sum a_i u1_i) G + sum (a_i u2_i) Q_i + sum a_i (-R_i) = O
+// for random a_i, as a single multi_mul. The a_i are only half the bit length of n (at most 128
+// bits) to keep the R_i terms short, which leaves a bad entry getting through with probability
+// about 2^-(bits of a_i)
+fn combined_holds<T: GenericUInt, R: RngCore + CryptoRng + ?Sized>(
+    domain: &CurveDomain<T>,
+    entries: &[CombinedEntry<T>],
+    rng: &mut R,
+) -> bool {
+    let n = domain.n();
+    let half_bits = (count_bits_generic_uint(n) / 2).clamp(1, 128);
+    let bound = T::from(1) << T::from(half_bits as u8);
+    let mut gcoeff = FpElem::new(T::from(0), n);
+    let mut terms = Vec::with_capacity(2 * entries.len() + 1);
+    for (pubkey, u1, u2, bigr) in entries.iter() {
+        let a = FpElem::new(get_nonzero_generic_uint_below(bound, rng), n);
+        gcoeff += a * u1;
+        terms.push((*pubkey, (a * u2).number));
+        terms.push((-bigr, a.number));
+    }
+    terms.push((domain.generator(), gcoeff.number));
+    domain.multi_mul(&terms).position.is_none()
+}
+
+// rebuild R = kG from r and the recovery id, None if there's no such point on the curve
+fn lift_r<T: GenericUInt>(
+    domain: &CurveDomain<T>,
+    signature: &RecoverableSignature<FpElem<T>>,
+) -> Option<ECPoint<FpElem<T>>> {
+    let r = signature.signature.targetx.number;
+    let id = signature.recovery_id;
+    let (p, n) = (domain.p, domain.n());
+    // x of R was r + n, which only fits below p if r < p - n (written that way to not overflow)
+    let x = if id & 2 != 0 {
+        if n >= p || r >= p - n {
+            return None;
+        }
+        r + n
+    } else {
+        r
+    };
+    ECPoint::from_x(x, id & 1 != 0, domain.a, domain.b, p)
+}
+
+// how many nonces sign will try before giving up - each one fails with probability ~1/n so
+// hitting this means something is badly wrong with the rng or the domain parameters
+const MAX_NONCE_ATTEMPTS: usize = 64;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
 pub struct Signature<FE> {
     // r is x value of "target" R = kG w/ k rand in Fp
     pub targetx: FE,
@@ -73,147 +210,406 @@ impl<T: GenericUInt> Signature<FpElem<T>> {
     pub fn new(r: FpElem<T>, s: FpElem<T>) -> Signature<FpElem<T>> {
         Signature { targetx: r, sig: s }
     }
+
+    // s and n - s both verify, "low" is the one <= n/2 (s lives mod n so n is just its prime)
+    pub fn is_low_s(&self) -> bool {
+        self.sig.number <= self.sig.prime >> T::from(1)
+    }
+
+    // the low-S twin of this signature, which is what Bitcoin and Ethereum insist on
+    pub fn normalize_s(&self) -> Self {
+        if self.is_low_s() {
+            *self
+        } else {
+            Signature {
+                targetx: self.targetx,
+                sig: FpElem::new(self.sig.prime - self.sig.number, self.sig.prime),
+            }
+        }
+    }
+}
+
+// A signature plus the two bits needed to rebuild R = kG from r, and so the public key from the
+// signature alone - bit 0 is the parity of R's y, bit 1 is set if R's x was >= n before reduction
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct RecoverableSignature<FE> {
+    pub signature: Signature<FE>,
+    pub recovery_id: u8,
+}
+
+impl<T: GenericUInt> RecoverableSignature<FpElem<T>> {
+    pub fn new(signature: Signature<FpElem<T>>, recovery_id: u8) -> Self {
+        RecoverableSignature {
+            signature,
+            recovery_id,
+        }
+    }
+
+    // swapping s for n - s swaps R for -R, so the y parity flips with it
+    pub fn normalize_s(&self) -> Self {
+        if self.signature.is_low_s() {
+            *self
+        } else {
+            RecoverableSignature {
+                signature: self.signature.normalize_s(),
+                recovery_id: self.recovery_id ^ 1,
+            }
+        }
+    }
+}
+
+// How the per signature nonce k is chosen
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum NonceMode {
+    // fresh randomness for every signature
+    #[default]
+    Random,
+    // RFC 6979 deterministic nonces, the same key and hash always give the same signature
+    // the HMAC uses the KeyChain's HashAlgorithm
+    Rfc6979,
 }
 
 pub struct KeyChain<T> {
     pub pubkey: ECPoint<FpElem<T>>,
-    generator: (ECPoint<FpElem<T>>, T),
+    domain: CurveDomain<T>,
     privkey: Option<FpElem<T>>,
+    nonce_mode: NonceMode,
+    // digest for sign_message/verify_message and the RFC 6979 HMAC
+    hash_algorithm: HashAlgorithm,
+    // always emit low-S signatures
+    low_s: bool,
+    // reject high-S signatures when verifying
+    strict: bool,
 }
 
 impl<T: GenericUInt> KeyChain<T> {
     pub fn new(
         privkey: Option<FpElem<T>>,
         pubkey: ECPoint<FpElem<T>>,
-        generator: (ECPoint<FpElem<T>>, T),
+        domain: CurveDomain<T>,
     ) -> Self {
         KeyChain {
             privkey,
             pubkey,
-            generator,
+            domain,
+            nonce_mode: NonceMode::default(),
+            hash_algorithm: HashAlgorithm::default(),
+            low_s: false,
+            strict: false,
         }
     }
 
-    pub fn new_pub(pubkey: ECPoint<FpElem<T>>, generator: (ECPoint<FpElem<T>>, T)) -> Self {
-        KeyChain {
-            privkey: None,
-            pubkey,
-            generator,
-        }
+    pub fn new_pub(pubkey: ECPoint<FpElem<T>>, domain: CurveDomain<T>) -> Self {
+        KeyChain::new(None, pubkey, domain)
     }
 
-    pub fn new_priv(privkey: FpElem<T>, generator: (ECPoint<FpElem<T>>, T)) -> Self {
-        let pubkey = &generator.0 * privkey;
-        KeyChain {
-            privkey: Some(privkey),
-            pubkey,
-            generator,
+    pub fn new_priv(privkey: FpElem<T>, domain: CurveDomain<T>) -> Self {
+        let pubkey = domain.generator_table().mul_secret(&privkey);
+        KeyChain::new(Some(privkey), pubkey, domain)
+    }
+
+    // fresh key pair with the private key uniform in [1, n-1]
+    pub fn generate<R: RngCore + CryptoRng + ?Sized>(
+        domain: CurveDomain<T>,
+        rng: &mut R,
+    ) -> Result<Self, KeyChainError> {
+        let privkey = FpElem::new(get_nonzero_generic_uint_below(domain.n(), rng), domain.n());
+        let keychain = KeyChain::new_priv(privkey, domain);
+        // only possible if the generator or its order is bogus
+        if keychain.pubkey.position.is_none() {
+            return Err(KeyChainError::PublicKeyAtInfinity);
         }
+        Ok(keychain)
     }
 
-    pub fn verify_sig(&self, hash: &FpElem<T>, signature: &Signature<FpElem<T>>) -> bool {
-        let u = hash / &signature.sig;
-        let v = &signature.targetx / &signature.sig;
-        if let Some((r, _)) = (&self.generator.0 * u + &self.pubkey * v).position {
-            let r = FpElem::new(r.number, self.generator.1);
-            return r == signature.targetx;
-        } else {
-            return false;
+    pub fn domain(&self) -> &CurveDomain<T> {
+        &self.domain
+    }
+
+    pub fn with_nonce_mode(mut self, nonce_mode: NonceMode) -> Self {
+        self.nonce_mode = nonce_mode;
+        self
+    }
+
+    pub fn with_hash_algorithm(mut self, hash_algorithm: HashAlgorithm) -> Self {
+        self.hash_algorithm = hash_algorithm;
+        self
+    }
+
+    pub fn with_low_s(mut self, low_s: bool) -> Self {
+        self.low_s = low_s;
+        self
+    }
+
+    pub fn with_strict_verification(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    pub fn verify_sig(
+        &self,
+        hash: &FpElem<T>,
+        signature: &Signature<FpElem<T>>,
+    ) -> Result<(), VerifyError> {
+        let (r, s) = (signature.targetx, signature.sig);
+        self.check_signature(hash, signature)?;
+        check_pubkey(&self.pubkey, &self.domain)?;
+        check_equation(&self.domain, &self.pubkey, &(hash / s), &(r / s), &r)
+    }
+
+    // the value checks plus this KeyChain's low-S policy, shared by verify_sig and verify_batch
+    fn check_signature(
+        &self,
+        hash: &FpElem<T>,
+        signature: &Signature<FpElem<T>>,
+    ) -> Result<(), VerifyError> {
+        check_signature_values(hash, signature, self.domain.n())?;
+        if self.strict && !signature.is_low_s() {
+            return Err(VerifyError::HighS);
         }
+        Ok(())
+    }
+
+    // hashes the message with the KeyChain's HashAlgorithm then signs as sign_prehash
+    pub fn sign_message(&self, message: &[u8]) -> Result<Signature<FpElem<T>>, KeyChainError> {
+        self.sign_message_with_rng(message, &mut thread_rng())
+    }
+
+    pub fn sign_message_with_rng<R: RngCore + CryptoRng + ?Sized>(
+        &self,
+        message: &[u8],
+        rng: &mut R,
+    ) -> Result<Signature<FpElem<T>>, KeyChainError> {
+        self.sign_prehash_with_rng(&self.hash_algorithm.digest(message), rng)
+    }
+
+    // for a digest computed elsewhere - it is truncated to the bit length of n (bits2int) and
+    // reduced mod n, so digests wider than n are handled the standard way
+    pub fn sign_prehash(&self, digest: &[u8]) -> Result<Signature<FpElem<T>>, KeyChainError> {
+        self.sign_prehash_with_rng(digest, &mut thread_rng())
+    }
+
+    pub fn sign_prehash_with_rng<R: RngCore + CryptoRng + ?Sized>(
+        &self,
+        digest: &[u8],
+        rng: &mut R,
+    ) -> Result<Signature<FpElem<T>>, KeyChainError> {
+        self.sign_with_rng(&digest_to_scalar(digest, self.domain.n()), rng)
+    }
+
+    pub fn verify_message(
+        &self,
+        message: &[u8],
+        signature: &Signature<FpElem<T>>,
+    ) -> Result<(), VerifyError> {
+        self.verify_prehash(&self.hash_algorithm.digest(message), signature)
+    }
+
+    pub fn verify_prehash(
+        &self,
+        digest: &[u8],
+        signature: &Signature<FpElem<T>>,
+    ) -> Result<(), VerifyError> {
+        self.verify_sig(&digest_to_scalar(digest, self.domain.n()), signature)
     }
 
+    // convenience wrapper on the thread rng - use sign_with_rng to pick the entropy source yourself
     pub fn sign(&self, hash: &FpElem<T>) -> Result<Signature<FpElem<T>>, KeyChainError> {
-        if let Some(privkey) = self.privkey {
-            let gen = self.generator.0;
-            // TODO: this
-            let k = FpElem::new(get_generic_uint_below(hash.number), self.generator.1);
-            let r = &gen * k;
-            // catch for point at infinity - just try again
-            if let Some((r, _)) = r.position {
-                let r = FpElem::new(r.number, self.generator.1); //r.to_field(privkeyring.generator.1);
-                let s = &(hash + &(&r * &privkey)) / &k;
-                return Ok(Signature { targetx: r, sig: s });
-            } else {
-                return self.sign(hash);
+        self.sign_with_rng(hash, &mut thread_rng())
+    }
+
+    // rng is only drawn from in NonceMode::Random
+    pub fn sign_with_rng<R: RngCore + CryptoRng + ?Sized>(
+        &self,
+        hash: &FpElem<T>,
+        rng: &mut R,
+    ) -> Result<Signature<FpElem<T>>, KeyChainError> {
+        Ok(self.sign_recoverable_with_rng(hash, rng)?.signature)
+    }
+
+    pub fn sign_recoverable(
+        &self,
+        hash: &FpElem<T>,
+    ) -> Result<RecoverableSignature<FpElem<T>>, KeyChainError> {
+        self.sign_recoverable_with_rng(hash, &mut thread_rng())
+    }
+
+    pub fn sign_recoverable_with_rng<R: RngCore + CryptoRng + ?Sized>(
+        &self,
+        hash: &FpElem<T>,
+        rng: &mut R,
+    ) -> Result<RecoverableSignature<FpElem<T>>, KeyChainError> {
+        let privkey = self.privkey.ok_or(KeyChainError::NoPrivateKey)?;
+        let n = self.domain.n();
+        let mut nonces = None;
+        for _ in 0..MAX_NONCE_ATTEMPTS {
+            let k = match self.nonce_mode {
+                NonceMode::Random => get_nonzero_generic_uint_below(n, rng),
+                NonceMode::Rfc6979 => nonces
+                    .get_or_insert_with(|| {
+                        DynRfc6979::new(self.hash_algorithm, privkey.number, hash.number, n)
+                    })
+                    .next_k(),
+            };
+            // catch for point at infinity or s = 0 - just try again with the next nonce
+            if let Some(signature) = self.sign_with_nonce(hash, &privkey, k) {
+                if self.low_s {
+                    return Ok(signature.normalize_s());
+                }
+                return Ok(signature);
+            }
+        }
+        Err(KeyChainError::NonceRetriesExhausted(MAX_NONCE_ATTEMPTS))
+    }
+
+    // None if k is unusable, i.e. r or s came out as 0
+    fn sign_with_nonce(
+        &self,
+        hash: &FpElem<T>,
+        privkey: &FpElem<T>,
+        k: T,
+    ) -> Option<RecoverableSignature<FpElem<T>>> {
+        let zero = T::from(0);
+        let one = T::from(1);
+        let n = self.domain.n();
+        let k = FpElem::new(k, n);
+        let (x, y) = self.domain.generator_table().mul_secret(&k).position?;
+        let r = FpElem::new(x.number, n);
+        if r.number == zero {
+            return None;
+        }
+        // s = (z + r d) / k worked out in Montgomery form mod n, with 1/k as k^(n-2) rather than
+        // by extended Euclid so neither d nor k is branched on
+        let params = MontgomeryParams::new(n);
+        let mont = |x: &FpElem<T>| MontFpElem::from_reduced(x.number, &params);
+        let s = (mont(hash) + mont(&r) * mont(privkey)) * mont(&k).pow(n - T::from(2));
+        let s = s.to_fp();
+        if s.number == zero {
+            return None;
+        }
+        let mut recovery_id = 0;
+        if y.number & one != zero {
+            recovery_id |= 1;
+        }
+        if x.number >= n {
+            recovery_id |= 2;
+        }
+        Some(RecoverableSignature {
+            signature: Signature { targetx: r, sig: s },
+            recovery_id,
+        })
+    }
+
+    // Q = r^-1 (sR - zG), with R rebuilt from r and the recovery id
+    pub fn recover_pubkey(
+        domain: &CurveDomain<T>,
+        hash: &FpElem<T>,
+        signature: &RecoverableSignature<FpElem<T>>,
+    ) -> Result<ECPoint<FpElem<T>>, RecoveryError> {
+        let (g, n) = (domain.generator(), domain.n());
+        let zero = T::from(0);
+        let (r, s) = (signature.signature.targetx, signature.signature.sig);
+        let id = signature.recovery_id;
+        if hash.prime != n || r.prime != n || s.prime != n {
+            return Err(RecoveryError::WrongModulus);
+        }
+        if r.number == zero || r.number >= n {
+            return Err(RecoveryError::ROutOfRange);
+        }
+        if s.number == zero || s.number >= n {
+            return Err(RecoveryError::SOutOfRange);
+        }
+        if id > 3 {
+            return Err(RecoveryError::InvalidRecoveryId(id));
+        }
+
+        let bigr = lift_r(domain, signature).ok_or(RecoveryError::NoPointForR)?;
+        if !bigr.is_in_subgroup(n, domain.cofactor) {
+            return Err(RecoveryError::NoPointForR);
+        }
+
+        let u = (FpElem::new(zero, n) - hash) / r;
+        let v = s / r;
+        let pubkey = domain.multi_mul(&[(g, u.number), (bigr, v.number)]);
+        if pubkey.position.is_none() {
+            return Err(RecoveryError::PubKeyAtInfinity);
+        }
+        Ok(pubkey)
+    }
+
+    // Checks a whole batch of (public key, digest, signature, recovery id) entries against this
+    // KeyChain's domain and strict setting - its own public key isn't used. A valid entry has
+    // u1_i G + u2_i Q_i = R_i, and with the recovery id R_i is known exactly, so every such entry
+    // goes into one random linear combination (see combined_holds). That's one multi scalar
+    // multiplication over all of them rather than one per signature.
+    // When the combination fails it is split in half and each half checked again, down to single
+    // entries which are checked exactly as verify_sig does, as are entries with no recovery id or
+    // no point for it. A valid signature under a wrong recovery id just ends up checked on its
+    // own, so the result always agrees with verify_sig.
+    // On curves with a cofactor other than 1 every entry is checked on its own - R_i could then
+    // carry a small order part that the a_i cancel - and the public keys' subgroup checks cost a
+    // scalar multiplication each anyway.
+    pub fn verify_batch<R: RngCore + CryptoRng + ?Sized>(
+        &self,
+        entries: &[BatchEntry<T>],
+        rng: &mut R,
+    ) -> Result<(), BatchVerifyError> {
+        let domain = &self.domain;
+        let n = domain.n();
+        let combine = domain.cofactor == T::from(1);
+        let mut invalid = Vec::new();
+        // (index, u1, u2) for what has to be checked on its own, the rest get combined
+        let mut single = Vec::new();
+        let mut combined = Vec::new();
+        let mut combined_index = Vec::new();
+        for (i, (pubkey, digest, signature, recovery_id)) in entries.iter().enumerate() {
+            let hash = digest_to_scalar(digest, n);
+            let checked = self
+                .check_signature(&hash, signature)
+                .and_then(|_| check_pubkey(pubkey, domain));
+            if let Err(err) = checked {
+                invalid.push((i, err));
+                continue;
+            }
+            // s is in [1, n-1] by now so one inverse serves both
+            let w = FpElem::new(T::from(1), n) / signature.sig;
+            let (u1, u2) = (hash * w, signature.targetx * w);
+            let bigr = recovery_id
+                .filter(|id| combine && *id <= 3)
+                .and_then(|id| lift_r(domain, &RecoverableSignature::new(*signature, id)));
+            match bigr {
+                Some(bigr) => {
+                    combined.push((*pubkey, u1, u2, bigr));
+                    combined_index.push(i);
+                }
+                None => single.push((i, u1, u2)),
+            }
+        }
+
+        // [start, end) ranges of combined still to check
+        let mut ranges = vec![(0, combined.len())];
+        while let Some((start, end)) = ranges.pop() {
+            if end - start == 1 {
+                let (_, u1, u2, _) = combined[start];
+                single.push((combined_index[start], u1, u2));
+            } else if end > start && !combined_holds(domain, &combined[start..end], rng) {
+                let mid = start + (end - start) / 2;
+                ranges.push((start, mid));
+                ranges.push((mid, end));
+            }
+        }
+        for (i, u1, u2) in single {
+            let (pubkey, _, signature, _) = &entries[i];
+            if let Err(err) = check_equation(domain, pubkey, &u1, &u2, &signature.targetx) {
+                invalid.push((i, err));
             }
         }
-        Err(KeyChainError::NoPrivateKey)
-    }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn test_count_bits_generic_uint() {
-        assert_eq!(count_bits_generic_uint(0u32), 1);
-        assert_eq!(count_bits_generic_uint(1u32), 1);
-        assert_eq!(count_bits_generic_uint(2u32), 2);
-        assert_eq!(count_bits_generic_uint(3u32), 2);
-        assert_eq!(count_bits_generic_uint(255u32), 8);
-    }
-
-    #[test]
-    fn test_get_generic_uint_below() {
-        let under = 30u32;
-        for _ in 0..100000 {
-            let result = get_generic_uint_below(under);
-            assert!(result < under);
-            // Check the distribution is approximately uniform
-        }
-        let mut counts = vec![0; under as usize];
-        for _ in 0..100000 {
-            let result = get_generic_uint_below(under);
-            assert!(result < under);
-            counts[result as usize] += 1;
-        }
-        let expected_count = 100000 / under;
-        let tolerance = expected_count / 10; // 10% tolerance
-        assert!((counts[4] as i32 - expected_count as i32).abs() <= tolerance as i32);
-        //
-        // // Plot the distribution
-        // use plotters::prelude::*;
-        //
-        // let root = BitMapBackend::new("random_distribution.png", (640, 480))
-        //     .into_drawing_area();
-        // root.fill(&WHITE).unwrap();
-        //
-        // let max_count = *counts.iter().max().unwrap() as f32;
-        // let mut chart = ChartBuilder::on(&root)
-        //     .caption("Distribution of Random Values", ("sans-serif", 30))
-        //     .margin(5)
-        //     .x_label_area_size(30)
-        //     .y_label_area_size(30)
-        //     .build_cartesian_2d(0..under as i32, 0f32..max_count)
-        //     .unwrap();
-        //
-        // chart.configure_mesh().draw().unwrap();
-        //
-        // // Plot actual distribution
-        // chart
-        //     .draw_series(
-        //         Histogram::vertical(&chart)
-        //             .style(BLUE.mix(0.5).filled())
-        //             .data(counts.iter().enumerate().map(|(x, y)| (x as i32, *y as f32)))
-        //     )
-        //     .unwrap()
-        //     .label("Actual Distribution");
-        //
-        // // Plot expected line
-        // chart
-        //     .draw_series(LineSeries::new(
-        //         (0..under as i32).map(|x| (x, expected_count as f32)),
-        //         &RED,
-        //     ))
-        //     .unwrap()
-        //     .label("Expected Value");
-        //
-        // chart
-        //     .configure_series_labels()
-        //     .background_style(&WHITE.mix(0.8))
-        //     .border_style(&BLACK)
-        //     .draw()
-        //     .unwrap();
+
+        if invalid.is_empty() {
+            Ok(())
+        } else {
+            invalid.sort_by_key(|(i, _)| *i);
+            Err(BatchVerifyError { invalid })
+        }
     }
 }
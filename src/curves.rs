@@ -0,0 +1,396 @@
+use crate::ellipticcurve::{CurveError, ECPoint};
+use crate::fixedbase::FixedBaseTable;
+use crate::fpelem::{generic_uint_from_be_bytes, generic_uint_to_be_bytes, FpElem, GenericUInt};
+use crate::glv::Endomorphism;
+use std::fmt;
+use std::fmt::Debug;
+use std::sync::{Arc, Mutex};
+
+// Domain parameters (SEC1 3.1.1) - the curve y^2 = x^3 + ax + b over F_p, a generator G of prime
+// order n, and the cofactor h = #E(F_p) / n
+// Not Copy as it carries the generator's table, clone it to share that. The generator and n are
+// what the table is built from, so they're only set by the constructors and read with getters
+#[derive(Clone)]
+pub struct CurveDomain<T> {
+    pub name: &'static str,
+    pub p: T,
+    pub a: T,
+    pub b: T,
+    generator: ECPoint<FpElem<T>>,
+    n: T,
+    pub cofactor: T,
+    // (x, y) -> (beta x, y) on a = 0 curves, for GLV scalar multiplication. Only set through
+    // with_endomorphism and the built in curves, as nothing else checks it
+    pub(crate) endomorphism: Option<Endomorphism<T>>,
+    // fixed base table for the generator, built the first time a key or signature needs one and
+    // shared by every clone of the domain
+    pub(crate) generator_table: Arc<Mutex<Option<Arc<FixedBaseTable<T>>>>>,
+}
+
+// the table cache isn't part of what the domain is
+impl<T: GenericUInt> PartialEq for CurveDomain<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+            && self.p == other.p
+            && self.a == other.a
+            && self.b == other.b
+            && self.generator == other.generator
+            && self.n == other.n
+            && self.cofactor == other.cofactor
+            && self.endomorphism == other.endomorphism
+    }
+}
+
+// derive would want T: Debug for ECPoint's Debug, which is only there for T: GenericUInt + Debug
+impl<T: GenericUInt + Debug> Debug for CurveDomain<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CurveDomain")
+            .field("name", &self.name)
+            .field("p", &self.p)
+            .field("a", &self.a)
+            .field("b", &self.b)
+            .field("generator", &self.generator)
+            .field("n", &self.n)
+            .field("cofactor", &self.cofactor)
+            .field("endomorphism", &self.endomorphism)
+            .finish()
+    }
+}
+
+// "ff01" -> [0xff, 0x01], odd lengths get a leading zero nibble
+fn hex_to_bytes(hex: &str) -> Vec<u8> {
+    let padded = if hex.len() % 2 == 1 {
+        format!("0{}", hex)
+    } else {
+        hex.to_string()
+    };
+    (0..padded.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&padded[i..i + 2], 16).expect("curve constants are hex"))
+        .collect()
+}
+
+impl<T: GenericUInt> CurveDomain<T> {
+    // p, a and b are read off the generator, which has to be an actual point on its curve
+    pub fn new(
+        name: &'static str,
+        generator: ECPoint<FpElem<T>>,
+        n: T,
+        cofactor: T,
+    ) -> Result<Self, CurveError> {
+        if generator.position.is_none() {
+            return Err(CurveError::GeneratorAtInfinity);
+        }
+        if !generator.is_on_curve() {
+            return Err(CurveError::NotOnCurve);
+        }
+        let (a, b) = generator.curve();
+        Ok(CurveDomain {
+            name,
+            p: a.prime,
+            a: a.number,
+            b: b.number,
+            generator,
+            n,
+            cofactor,
+            endomorphism: None,
+            generator_table: Arc::default(),
+        })
+    }
+
+    pub fn generator(&self) -> ECPoint<FpElem<T>> {
+        self.generator
+    }
+
+    pub fn n(&self) -> T {
+        self.n
+    }
+
+    pub fn endomorphism(&self) -> Option<&Endomorphism<T>> {
+        self.endomorphism.as_ref()
+    }
+
+    // the fixed base table for the generator, built on first use. KeyChain multiplies the
+    // generator by private keys and nonces through this
+    pub fn generator_table(&self) -> Arc<FixedBaseTable<T>> {
+        self.generator_table
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .get_or_insert_with(|| Arc::new(FixedBaseTable::new(&self.generator, self.n)))
+            .clone()
+    }
+
+    // the built in curves below, by name - the SEC2 names of the NIST curves work too
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "secp256k1" => Some(CurveDomain::secp256k1()),
+            "secp224k1" => Some(CurveDomain::secp224k1()),
+            "P-224" | "secp224r1" => Some(CurveDomain::p224()),
+            "P-256" | "secp256r1" | "prime256v1" => Some(CurveDomain::p256()),
+            "P-384" | "secp384r1" => Some(CurveDomain::p384()),
+            "P-521" | "secp521r1" => Some(CurveDomain::p521()),
+            "brainpoolP160r1" => Some(CurveDomain::brainpool_p160r1()),
+            "brainpoolP192r1" => Some(CurveDomain::brainpool_p192r1()),
+            "brainpoolP224r1" => Some(CurveDomain::brainpool_p224r1()),
+            "brainpoolP256r1" => Some(CurveDomain::brainpool_p256r1()),
+            "brainpoolP320r1" => Some(CurveDomain::brainpool_p320r1()),
+            "brainpoolP384r1" => Some(CurveDomain::brainpool_p384r1()),
+            "brainpoolP512r1" => Some(CurveDomain::brainpool_p512r1()),
+            _ => None,
+        }
+    }
+
+    // params is p, a, b, Gx, Gy, n in hex
+    // panics if T can't hold them - the field arithmetic never forms anything above p or n, so
+    // they can take up all of T
+    fn named(name: &'static str, params: [&str; 6], cofactor: u8) -> Self {
+        let [p, a, b, gx, gy, n] = params.map(|hex| {
+            let bytes = hex_to_bytes(hex);
+            let x: T = generic_uint_from_be_bytes(&bytes);
+            assert!(
+                generic_uint_to_be_bytes(x, bytes.len()) == bytes,
+                "{} does not fit in this integer type",
+                name
+            );
+            x
+        });
+        CurveDomain {
+            name,
+            p,
+            a,
+            b,
+            generator: ECPoint::new(gx, gy, a, b, p),
+            n,
+            cofactor: T::from(cofactor),
+            endomorphism: None,
+            generator_table: Arc::default(),
+        }
+    }
+
+    // attaches the endomorphism of one of the a = 0 built in curves - the constants are checked
+    // in the tests rather than with a scalar multiplication every time the curve is built
+    fn with_known_endomorphism(self, beta: &str, lambda: &str) -> Self {
+        let [beta, lambda] =
+            [beta, lambda].map(|hex| generic_uint_from_be_bytes(&hex_to_bytes(hex)));
+        CurveDomain {
+            endomorphism: Some(Endomorphism::new(beta, lambda, self.n)),
+            ..self
+        }
+    }
+
+    // SEC2 2.4.1, the Bitcoin and Ethereum curve
+    pub fn secp256k1() -> Self {
+        CurveDomain::named(
+            "secp256k1",
+            [
+                "fffffffffffffffffffffffffffffffffffffffffffffffffffffffefffffc2f",
+                "0",
+                "7",
+                "79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798",
+                "483ada7726a3c4655da4fbfc0e1108a8fd17b448a68554199c47d08ffb10d4b8",
+                "fffffffffffffffffffffffffffffffebaaedce6af48a03bbfd25e8cd0364141",
+            ],
+            1,
+        )
+        .with_known_endomorphism(
+            "7ae96a2b657c07106e64479eac3434e99cf0497512f58995c1396c28719501ee",
+            "5363ad4cc05c30e0a5261c028812645a122e22ea20816678df02967c1b23bd72",
+        )
+    }
+
+    // SEC2 2.3.1 - n is a bit wider than p here
+    pub fn secp224k1() -> Self {
+        CurveDomain::named(
+            "secp224k1",
+            [
+                "fffffffffffffffffffffffffffffffffffffffffffffffeffffe56d",
+                "0",
+                "5",
+                "a1455b334df099df30fc28a169a467e9e47075a90f7e650eb6b7a45c",
+                "7e089fed7fba344282cafbd6f7e319f7c0b0bd59e2ca4bdb556d61a5",
+                "10000000000000000000000000001dce8d2ec6184caf0a971769fb1f7",
+            ],
+            1,
+        )
+        .with_known_endomorphism(
+            "fe0e87005b4e83761908c5131d552a850b3f58b749c37cf5b84d6768",
+            "60dcd2104c4cbc0be6eeefc2bdd610739ec34e317f9b33046c9e4788",
+        )
+    }
+
+    // FIPS 186-4 D.1.2.2, also known as secp224r1
+    pub fn p224() -> Self {
+        CurveDomain::named(
+            "P-224",
+            [
+                "ffffffffffffffffffffffffffffffff000000000000000000000001",
+                "fffffffffffffffffffffffffffffffefffffffffffffffffffffffe",
+                "b4050a850c04b3abf54132565044b0b7d7bfd8ba270b39432355ffb4",
+                "b70e0cbd6bb4bf7f321390b94a03c1d356c21122343280d6115c1d21",
+                "bd376388b5f723fb4c22dfe6cd4375a05a07476444d5819985007e34",
+                "ffffffffffffffffffffffffffff16a2e0b8f03e13dd29455c5c2a3d",
+            ],
+            1,
+        )
+    }
+
+    // FIPS 186-4 D.1.2.3, also known as secp256r1 and prime256v1
+    pub fn p256() -> Self {
+        CurveDomain::named(
+            "P-256",
+            [
+                "ffffffff00000001000000000000000000000000ffffffffffffffffffffffff",
+                "ffffffff00000001000000000000000000000000fffffffffffffffffffffffc",
+                "5ac635d8aa3a93e7b3ebbd55769886bc651d06b0cc53b0f63bce3c3e27d2604b",
+                "6b17d1f2e12c4247f8bce6e563a440f277037d812deb33a0f4a13945d898c296",
+                "4fe342e2fe1a7f9b8ee7eb4a7c0f9e162bce33576b315ececbb6406837bf51f5",
+                "ffffffff00000000ffffffffffffffffbce6faada7179e84f3b9cac2fc632551",
+            ],
+            1,
+        )
+    }
+
+    // FIPS 186-4 D.1.2.4, also known as secp384r1
+    pub fn p384() -> Self {
+        CurveDomain::named(
+            "P-384",
+            [
+                "fffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffeffffffff0000000000000000ffffffff",
+                "fffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffeffffffff0000000000000000fffffffc",
+                "b3312fa7e23ee7e4988e056be3f82d19181d9c6efe8141120314088f5013875ac656398d8a2ed19d2a85c8edd3ec2aef",
+                "aa87ca22be8b05378eb1c71ef320ad746e1d3b628ba79b9859f741e082542a385502f25dbf55296c3a545e3872760ab7",
+                "3617de4a96262c6f5d9e98bf9292dc29f8f41dbd289a147ce9da3113b5f0b8c00a60b1ce1d7e819d7a431d7c90ea0e5f",
+                "ffffffffffffffffffffffffffffffffffffffffffffffffc7634d81f4372ddf581a0db248b0a77aecec196accc52973",
+            ],
+            1,
+        )
+    }
+
+    // FIPS 186-4 D.1.2.5, also known as secp521r1 - needs a T of at least 521 bits
+    pub fn p521() -> Self {
+        CurveDomain::named(
+            "P-521",
+            [
+                "1ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff",
+                "1fffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffc",
+                "051953eb9618e1c9a1f929a21a0b68540eea2da725b99b315f3b8b489918ef109e156193951ec7e937b1652c0bd3bb1bf073573df883d2c34f1ef451fd46b503f00",
+                "0c6858e06b70404e9cd9e3ecb662395b4429c648139053fb521f828af606b4d3dbaa14b5e77efe75928fe1dc127a2ffa8de3348b3c1856a429bf97e7e31c2e5bd66",
+                "11839296a789a3bc0045c8a5fb42c7d1bd998f54449579b446817afbd17273e662c97ee72995ef42640c550b9013fad0761353c7086a272c24088be94769fd16650",
+                "1fffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffa51868783bf2f966b7fcc0148f709a5d03bb5c9b8899c47aebb6fb71e91386409",
+            ],
+            1,
+        )
+    }
+
+    // RFC 5639 3.1
+    pub fn brainpool_p160r1() -> Self {
+        CurveDomain::named(
+            "brainpoolP160r1",
+            [
+                "e95e4a5f737059dc60dfc7ad95b3d8139515620f",
+                "340e7be2a280eb74e2be61bada745d97e8f7c300",
+                "1e589a8595423412134faa2dbdec95c8d8675e58",
+                "bed5af16ea3f6a4f62938c4631eb5af7bdbcdbc3",
+                "1667cb477a1a8ec338f94741669c976316da6321",
+                "e95e4a5f737059dc60df5991d45029409e60fc09",
+            ],
+            1,
+        )
+    }
+
+    // RFC 5639 3.2
+    pub fn brainpool_p192r1() -> Self {
+        CurveDomain::named(
+            "brainpoolP192r1",
+            [
+                "c302f41d932a36cda7a3463093d18db78fce476de1a86297",
+                "6a91174076b1e0e19c39c031fe8685c1cae040e5c69a28ef",
+                "469a28ef7c28cca3dc721d044f4496bcca7ef4146fbf25c9",
+                "c0a0647eaab6a48753b033c56cb0f0900a2f5c4853375fd6",
+                "14b690866abd5bb88b5f4828c1490002e6773fa2fa299b8f",
+                "c302f41d932a36cda7a3462f9e9e916b5be8f1029ac4acc1",
+            ],
+            1,
+        )
+    }
+
+    // RFC 5639 3.3
+    pub fn brainpool_p224r1() -> Self {
+        CurveDomain::named(
+            "brainpoolP224r1",
+            [
+                "d7c134aa264366862a18302575d1d787b09f075797da89f57ec8c0ff",
+                "68a5e62ca9ce6c1c299803a6c1530b514e182ad8b0042a59cad29f43",
+                "2580f63ccfe44138870713b1a92369e33e2135d266dbb372386c400b",
+                "0d9029ad2c7e5cf4340823b2a87dc68c9e4ce3174c1e6efdee12c07d",
+                "58aa56f772c0726f24c6b89e4ecdac24354b9e99caa3f6d3761402cd",
+                "d7c134aa264366862a18302575d0fb98d116bc4b6ddebca3a5a7939f",
+            ],
+            1,
+        )
+    }
+
+    // RFC 5639 3.4
+    pub fn brainpool_p256r1() -> Self {
+        CurveDomain::named(
+            "brainpoolP256r1",
+            [
+                "a9fb57dba1eea9bc3e660a909d838d726e3bf623d52620282013481d1f6e5377",
+                "7d5a0975fc2c3057eef67530417affe7fb8055c126dc5c6ce94a4b44f330b5d9",
+                "26dc5c6ce94a4b44f330b5d9bbd77cbf958416295cf7e1ce6bccdc18ff8c07b6",
+                "8bd2aeb9cb7e57cb2c4b482ffc81b7afb9de27e1e3bd23c23a4453bd9ace3262",
+                "547ef835c3dac4fd97f8461a14611dc9c27745132ded8e545c1d54c72f046997",
+                "a9fb57dba1eea9bc3e660a909d838d718c397aa3b561a6f7901e0e82974856a7",
+            ],
+            1,
+        )
+    }
+
+    // RFC 5639 3.5
+    pub fn brainpool_p320r1() -> Self {
+        CurveDomain::named(
+            "brainpoolP320r1",
+            [
+                "d35e472036bc4fb7e13c785ed201e065f98fcfa6f6f40def4f92b9ec7893ec28fcd412b1f1b32e27",
+                "3ee30b568fbab0f883ccebd46d3f3bb8a2a73513f5eb79da66190eb085ffa9f492f375a97d860eb4",
+                "520883949dfdbc42d3ad198640688a6fe13f41349554b49acc31dccd884539816f5eb4ac8fb1f1a6",
+                "43bd7e9afb53d8b85289bcc48ee5bfe6f20137d10a087eb6e7871e2a10a599c710af8d0d39e20611",
+                "14fdd05545ec1cc8ab4093247f77275e0743ffed117182eaa9c77877aaac6ac7d35245d1692e8ee1",
+                "d35e472036bc4fb7e13c785ed201e065f98fcfa5b68f12a32d482ec7ee8658e98691555b44c59311",
+            ],
+            1,
+        )
+    }
+
+    // RFC 5639 3.6
+    pub fn brainpool_p384r1() -> Self {
+        CurveDomain::named(
+            "brainpoolP384r1",
+            [
+                "8cb91e82a3386d280f5d6f7e50e641df152f7109ed5456b412b1da197fb71123acd3a729901d1a71874700133107ec53",
+                "7bc382c63d8c150c3c72080ace05afa0c2bea28e4fb22787139165efba91f90f8aa5814a503ad4eb04a8c7dd22ce2826",
+                "04a8c7dd22ce28268b39b55416f0447c2fb77de107dcd2a62e880ea53eeb62d57cb4390295dbc9943ab78696fa504c11",
+                "1d1c64f068cf45ffa2a63a81b7c13f6b8847a3e77ef14fe3db7fcafe0cbd10e8e826e03436d646aaef87b2e247d4af1e",
+                "8abe1d7520f9c2a45cb1eb8e95cfd55262b70b29feec5864e19c054ff99129280e4646217791811142820341263c5315",
+                "8cb91e82a3386d280f5d6f7e50e641df152f7109ed5456b31f166e6cac0425a7cf3ab6af6b7fc3103b883202e9046565",
+            ],
+            1,
+        )
+    }
+
+    // RFC 5639 3.7 - needs a T of at least 512 bits
+    pub fn brainpool_p512r1() -> Self {
+        CurveDomain::named(
+            "brainpoolP512r1",
+            [
+                "aadd9db8dbe9c48b3fd4e6ae33c9fc07cb308db3b3c9d20ed6639cca703308717d4d9b009bc66842aecda12ae6a380e62881ff2f2d82c68528aa6056583a48f3",
+                "7830a3318b603b89e2327145ac234cc594cbdd8d3df91610a83441caea9863bc2ded5d5aa8253aa10a2ef1c98b9ac8b57f1117a72bf2c7b9e7c1ac4d77fc94ca",
+                "3df91610a83441caea9863bc2ded5d5aa8253aa10a2ef1c98b9ac8b57f1117a72bf2c7b9e7c1ac4d77fc94cadc083e67984050b75ebae5dd2809bd638016f723",
+                "81aee4bdd82ed9645a21322e9c4c6a9385ed9f70b5d916c1b43b62eef4d0098eff3b1f78e2d0d48d50d1687b93b97d5f7c6d5047406a5e688b352209bcb9f822",
+                "7dde385d566332ecc0eabfa9cf7822fdf209f70024a57b1aa000c55b881f8111b2dcde494a5f485e5bca4bd88a2763aed1ca2b2fa8f0540678cd1e0f3ad80892",
+                "aadd9db8dbe9c48b3fd4e6ae33c9fc07cb308db3b3c9d20ed6639cca70330870553e5c414ca92619418661197fac10471db1d381085ddaddb58796829ca90069",
+            ],
+            1,
+        )
+    }
+}
@@ -1,7 +1,26 @@
-use crate::fpelem::{FpElem, GenericUInt, Pow};
+use crate::fpelem::{
+    count_bits_generic_uint, ArithmeticError, FieldElement, FpElem, GenericUInt, Pow,
+};
+use crate::jacobian::JacobianPoint;
+use crate::montgomery::MontgomeryParams;
+use crate::projective::ProjectivePoint;
 use std::fmt;
 use std::fmt::Debug;
-use std::ops::{Add, Mul};
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Mul, MulAssign, Neg, Sub, SubAssign};
+use thiserror::Error;
+
+#[derive(Error, Debug, PartialEq, Clone)]
+pub enum CurveError {
+    #[error("point is not on the curve y^2 = x^3 + ax + b")]
+    NotOnCurve,
+    #[error("coordinate is not below the field prime")]
+    CoordinateOutOfRange,
+    #[error("generator is the point at infinity")]
+    GeneratorAtInfinity,
+    #[error("beta and lambda don't give an endomorphism of this curve")]
+    InvalidEndomorphism,
+}
 
 #[derive(PartialEq, Clone, Copy)]
 pub struct ECPoint<T> {
@@ -12,101 +31,153 @@ pub struct ECPoint<T> {
 }
 
 // Formatting is very ugly else
-impl<T: Debug + Copy> Debug for ECPoint<FpElem<T>> {
+impl<F: FieldElement> Debug for ECPoint<F>
+where
+    F::Int: Debug,
+{
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         // case bash infinity or not
         if let Some((x, y)) = self.position {
-            return f
-                .debug_struct("ECPoint")
-                .field("pos", &(x.number, y.number))
-                .field("a", &self.a.number)
-                .field("b", &self.b.number)
-                .field("p", &self.a.prime)
-                .finish();
+            f.debug_struct("ECPoint")
+                .field("pos", &(x.to_int(), y.to_int()))
+                .field("a", &self.a.to_int())
+                .field("b", &self.b.to_int())
+                .field("p", &self.a.modulus())
+                .finish()
         } else {
-            return f
-                .debug_struct("ECPoint")
+            f.debug_struct("ECPoint")
                 .field("pos", &"infinity")
-                .field("a", &self.a.number)
-                .field("b", &self.b.number)
-                .field("p", &self.a.prime)
-                .finish();
+                .field("a", &self.a.to_int())
+                .field("b", &self.b.to_int())
+                .field("p", &self.a.modulus())
+                .finish()
         }
     }
 }
 
-impl<T: GenericUInt> Add for ECPoint<FpElem<T>> {
-    type Output = ECPoint<FpElem<T>>;
-    fn add(self, toadd: Self) -> ECPoint<FpElem<T>> {
-        assert!(
-            self.a == toadd.a && self.b == toadd.b,
-            "Curves must be the same"
-        );
-        if let (Some((x1, y1)), Some((x2, y2))) = (self.position, toadd.position) {
-            if (x1, y1) == (x2, y2) {
-                let s = &(&(&x1.pow(T::from(2)) * T::from(3)) + &self.a) / &(&y1 + &y1);
-                let x3 = &s.pow(T::from(2)) - &(&x1 * T::from(2));
-                let y3 = &(&s * &(&x1 - &x3)) - &y1;
-                return ECPoint {
-                    position: Some((x3, y3)),
-                    a: self.a,
-                    b: self.b,
-                };
-            } else if x1 == x2 {
-                return ECPoint {
-                    position: None,
-                    a: self.a,
-                    b: self.b,
-                };
-            } else {
-                let s = &(&y2 - &y1) / &(&x2 - &x1);
-                let x3 = &(&s.pow(T::from(2)) - &x1) - &x2;
-                let y3 = &(&s * &(&x1 - &x3)) - &y1;
-                return ECPoint {
-                    position: Some((x3, y3)),
-                    a: self.a,
-                    b: self.b,
-                };
-            }
-        } else if let Some((_x, _y)) = self.position {
-            return ECPoint {
-                position: self.position.clone(),
-                a: self.a,
-                b: self.b,
-            };
-        } else if let Some((_x, _y)) = toadd.position {
-            return ECPoint {
-                position: toadd.position.clone(),
-                a: self.a,
-                b: self.b,
-            };
-        }
-        return ECPoint {
-            position: None,
-            a: self.a,
-            b: self.b,
-        };
+// with the curves matching checked_add can only fail when p isn't prime, see FpElem::try_inverse
+impl<F: FieldElement> Add for ECPoint<F> {
+    type Output = ECPoint<F>;
+    fn add(self, toadd: Self) -> ECPoint<F> {
+        assert!(self.same_curve(&toadd), "Curves must be the same");
+        self.checked_add(&toadd)
+            .unwrap_or_else(|err| panic!("{}", err))
     }
 }
 
-impl<T: GenericUInt> Mul<T> for ECPoint<FpElem<T>> {
-    type Output = ECPoint<FpElem<T>>;
-    fn mul(self, tomul: T) -> ECPoint<FpElem<T>> {
-        let mut exp = tomul;
-        let mut result = ECPoint {
-            position: None,
+// -(x, y) = (x, -y), and infinity is its own negative
+impl<F: FieldElement> Neg for ECPoint<F> {
+    type Output = ECPoint<F>;
+    fn neg(self) -> ECPoint<F> {
+        ECPoint {
+            position: self.position.map(|(x, y)| (x, -y)),
             a: self.a,
             b: self.b,
-        };
-        let mut current = self;
-        while exp != T::from(0) {
-            if (exp & T::from(1)) != T::from(0) {
-                result = result + current;
-            }
-            current = current + current;
-            exp = exp >> T::from(1);
         }
-        result
+    }
+}
+
+impl<F: FieldElement> Neg for &ECPoint<F> {
+    type Output = ECPoint<F>;
+    fn neg(self) -> ECPoint<F> {
+        -*self
+    }
+}
+
+// the reference forms of Add, and Sub as adding the negative, all through the owned Add above
+impl<F: FieldElement> Add<&ECPoint<F>> for ECPoint<F> {
+    type Output = ECPoint<F>;
+    fn add(self, toadd: &ECPoint<F>) -> ECPoint<F> {
+        self + *toadd
+    }
+}
+
+impl<F: FieldElement> Add<ECPoint<F>> for &ECPoint<F> {
+    type Output = ECPoint<F>;
+    fn add(self, toadd: ECPoint<F>) -> ECPoint<F> {
+        *self + toadd
+    }
+}
+
+impl<F: FieldElement> Add for &ECPoint<F> {
+    type Output = ECPoint<F>;
+    fn add(self, toadd: &ECPoint<F>) -> ECPoint<F> {
+        *self + *toadd
+    }
+}
+
+impl<F: FieldElement> Sub for ECPoint<F> {
+    type Output = ECPoint<F>;
+    fn sub(self, tosub: ECPoint<F>) -> ECPoint<F> {
+        self + -tosub
+    }
+}
+
+impl<F: FieldElement> Sub<&ECPoint<F>> for ECPoint<F> {
+    type Output = ECPoint<F>;
+    fn sub(self, tosub: &ECPoint<F>) -> ECPoint<F> {
+        self + -tosub
+    }
+}
+
+impl<F: FieldElement> Sub<ECPoint<F>> for &ECPoint<F> {
+    type Output = ECPoint<F>;
+    fn sub(self, tosub: ECPoint<F>) -> ECPoint<F> {
+        *self + -tosub
+    }
+}
+
+impl<F: FieldElement> Sub for &ECPoint<F> {
+    type Output = ECPoint<F>;
+    fn sub(self, tosub: &ECPoint<F>) -> ECPoint<F> {
+        *self + -tosub
+    }
+}
+
+impl<F: FieldElement> AddAssign for ECPoint<F> {
+    fn add_assign(&mut self, toadd: ECPoint<F>) {
+        *self = *self + toadd;
+    }
+}
+
+impl<F: FieldElement> AddAssign<&ECPoint<F>> for ECPoint<F> {
+    fn add_assign(&mut self, toadd: &ECPoint<F>) {
+        *self = *self + *toadd;
+    }
+}
+
+impl<F: FieldElement> SubAssign for ECPoint<F> {
+    fn sub_assign(&mut self, tosub: ECPoint<F>) {
+        *self = *self - tosub;
+    }
+}
+
+impl<F: FieldElement> SubAssign<&ECPoint<F>> for ECPoint<F> {
+    fn sub_assign(&mut self, tosub: &ECPoint<F>) {
+        *self = *self - tosub;
+    }
+}
+
+// points.sum::<Option<_>>(), which is try_sum - there's no infinity to give back for no points
+// without a curve, so the sum is an Option rather than a point
+impl<F: FieldElement> Sum<ECPoint<F>> for Option<ECPoint<F>> {
+    fn sum<I: Iterator<Item = ECPoint<F>>>(iter: I) -> Self {
+        ECPoint::try_sum(iter)
+    }
+}
+
+impl<'a, F: FieldElement> Sum<&'a ECPoint<F>> for Option<ECPoint<F>> {
+    fn sum<I: Iterator<Item = &'a ECPoint<F>>>(iter: I) -> Self {
+        ECPoint::try_sum(iter.copied())
+    }
+}
+
+// width w NAF, done in Jacobian coordinates so there's only one inversion at the end - this is
+// variable time, see mul_secret for secret scalars
+impl<T: GenericUInt, F: FieldElement<Int = T>> Mul<T> for ECPoint<F> {
+    type Output = ECPoint<F>;
+    fn mul(self, tomul: T) -> ECPoint<F> {
+        ECPoint::straus(&[(self, tomul)])
     }
 }
 
@@ -118,6 +189,106 @@ impl<T: GenericUInt> Mul<FpElem<T>> for &ECPoint<FpElem<T>> {
     }
 }
 
+impl<T: GenericUInt, F: FieldElement<Int = T>> Mul<T> for &ECPoint<F> {
+    type Output = ECPoint<F>;
+    fn mul(self, tomul: T) -> ECPoint<F> {
+        *self * tomul
+    }
+}
+
+impl<T: GenericUInt, F: FieldElement<Int = T>> MulAssign<T> for ECPoint<F> {
+    fn mul_assign(&mut self, tomul: T) {
+        *self = *self * tomul;
+    }
+}
+
+impl<F: FieldElement> ECPoint<F> {
+    // chord and tangent, with a CurveMismatch for points on different curves rather than a panic
+    pub fn checked_add(&self, other: &Self) -> Result<Self, ArithmeticError> {
+        if !self.same_curve(other) {
+            return Err(ArithmeticError::CurveMismatch);
+        }
+        let position = match (self.position, other.position) {
+            (None, _) => other.position,
+            (_, None) => self.position,
+            (Some((x1, y1)), Some((x2, y2))) => {
+                // a point of order 2 (y = 0) doubles to infinity, there's no tangent slope to
+                // divide out
+                let slope = if (x1, y1) == (x2, y2) && !y1.is_zero() {
+                    let xx = x1 * x1;
+                    Some((xx + xx + xx + self.a) * (y1 + y1).try_inverse()?)
+                } else if x1 == x2 {
+                    None
+                } else {
+                    Some((y2 - y1) * (x2 - x1).try_inverse()?)
+                };
+                // x1 = x2 when doubling, so s^2 - x1 - x2 covers both
+                slope.map(|s| {
+                    let x3 = s * s - x1 - x2;
+                    (x3, s * (x1 - x3) - y1)
+                })
+            }
+        };
+        Ok(ECPoint {
+            position,
+            a: self.a,
+            b: self.b,
+        })
+    }
+
+    pub fn checked_sub(&self, other: &Self) -> Result<Self, ArithmeticError> {
+        self.checked_add(&-other)
+    }
+
+    // summed in Jacobian coordinates, so one inversion however many points. None for no points
+    // as there's no curve to put infinity on, and like + this panics on mixed curves
+    pub fn try_sum<I: IntoIterator<Item = Self>>(points: I) -> Option<Self> {
+        let mut points = points.into_iter();
+        let first = JacobianPoint::from_affine(&points.next()?);
+        Some(
+            points
+                .fold(first, |sum, point| sum + JacobianPoint::from_affine(&point))
+                .to_affine(),
+        )
+    }
+
+    // (a, b) of the curve this point lives on
+    pub(crate) fn curve(&self) -> (F, F) {
+        (self.a, self.b)
+    }
+
+    // the point at position on the curve y^2 = x^3 + ax + b, no checks
+    pub(crate) fn from_parts(position: Option<(F, F)>, a: F, b: F) -> Self {
+        ECPoint { position, a, b }
+    }
+
+    // infinity on the same curve as self
+    pub(crate) fn infinity(&self) -> Self {
+        ECPoint::from_parts(None, self.a, self.b)
+    }
+
+    // y^2 = x^3 + ax + b, with infinity counting as on the curve
+    pub fn is_on_curve(&self) -> bool {
+        match self.position {
+            Some((x, y)) => y * y == x * x * x + self.a * x + self.b,
+            None => true,
+        }
+    }
+
+    // in the subgroup of order n, where n * cofactor is the number of points on the curve
+    // with cofactor 1 that's the whole curve so being on it is enough, else check nP = O
+    pub fn is_in_subgroup(&self, order: F::Int, cofactor: F::Int) -> bool {
+        if !self.is_on_curve() {
+            return false;
+        }
+        cofactor == F::Int::from(1) || (*self * order).position.is_none()
+    }
+
+    pub(crate) fn same_curve(&self, other: &Self) -> bool {
+        self.a == other.a && self.b == other.b
+    }
+}
+
 impl<T: GenericUInt> ECPoint<FpElem<T>> {
     pub fn new(
         x: impl Into<T>,
@@ -134,6 +305,96 @@ impl<T: GenericUInt> ECPoint<FpElem<T>> {
         }
     }
 
+    // k * self for a secret k, by a Montgomery ladder over the complete projective formulas.
+    // Every bit costs one add and one double whatever its value, and the loop always runs for the
+    // bit length of k's modulus (the group order) rather than of k. The bits are only ever used
+    // as 0 or 1 multipliers in ProjectivePoint::swap_if, and the ladder runs on MontFpElem, whose
+    // add, sub and mul don't branch on their values, with the inversion at the end done by
+    // Fermat. So it's constant time as far as T's own +, -, *, & and shifts are.
+    // The steps add r0 and r1 with r1 - r0 = self so the formulas only break when self has even
+    // order - r0 comes out as (0 : 0 : 0) and this falls back to the Jacobian multiplication,
+    // which isn't constant time. Points of a CurveDomain's subgroup with n odd never get there.
+    pub fn mul_secret(&self, k: &FpElem<T>) -> Self {
+        let one = T::from(1);
+        let bits = count_bits_generic_uint(k.prime);
+        let mut expansion = Vec::with_capacity(bits as usize);
+        let mut rest = k.number;
+        for _ in 0..bits {
+            expansion.push(rest & one);
+            rest = rest >> one;
+        }
+        let params = MontgomeryParams::new(self.a.prime);
+        let point = self.to_montgomery(&params);
+        let (a, b) = point.curve();
+        let mut r0 = ProjectivePoint::from_affine(&ECPoint::from_parts(None, a, b));
+        let mut r1 = ProjectivePoint::from_affine(&point);
+        // r1 - r0 = self throughout
+        for bit in expansion.iter().rev() {
+            r0.swap_if(&mut r1, *bit);
+            r1 = r0 + r1;
+            r0 = r0.double();
+            r0.swap_if(&mut r1, *bit);
+        }
+        if r0.is_degenerate() {
+            return *self * k.number;
+        }
+        ECPoint::from(r0.to_affine())
+    }
+
+    // new, but refuses coordinates that new would reduce mod p and points that aren't on the curve
+    pub fn try_new(
+        x: impl Into<T>,
+        y: impl Into<T>,
+        a: impl Into<T>,
+        b: impl Into<T>,
+        prime: impl Into<T>,
+    ) -> Result<Self, CurveError> {
+        let (x, y, prime) = (x.into(), y.into(), prime.into());
+        if x >= prime || y >= prime {
+            return Err(CurveError::CoordinateOutOfRange);
+        }
+        let point = ECPoint::new(x, y, a, b, prime);
+        if !point.is_on_curve() {
+            return Err(CurveError::NotOnCurve);
+        }
+        Ok(point)
+    }
+
+    // the curve point with this x and the given parity of y, None if x^3 + ax + b isn't a square.
+    // Also None for x >= p, and for odd y when y = 0 as -0 = 0 is even
+    pub fn from_x(
+        x: impl Into<T>,
+        y_odd: bool,
+        a: impl Into<T>,
+        b: impl Into<T>,
+        prime: impl Into<T>,
+    ) -> Option<Self> {
+        let (x, prime) = (x.into(), prime.into());
+        if x >= prime {
+            return None;
+        }
+        let (x, a, b) = (
+            FpElem::new(x, prime),
+            FpElem::new_from(a, prime),
+            FpElem::new_from(b, prime),
+        );
+        let zero = T::from(0);
+        let y = (x.pow(T::from(3)) + a * x + b).sqrt()?;
+        if y.number == zero && y_odd {
+            return None;
+        }
+        let y = if ((y.number & T::from(1)) != zero) == y_odd {
+            y
+        } else {
+            FpElem::new(zero, prime) - y
+        };
+        Some(ECPoint {
+            position: Some((x, y)),
+            a,
+            b,
+        })
+    }
+
     pub fn new_infinity(a: impl Into<T>, b: impl Into<T>, prime: impl Into<T>) -> Self {
         let prime = prime.into();
         ECPoint {
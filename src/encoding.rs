@@ -0,0 +1,283 @@
+use crate::crypto::Signature;
+use crate::ellipticcurve::ECPoint;
+use crate::fpelem::{
+    count_bits_generic_uint, generic_uint_from_be_bytes, generic_uint_to_be_bytes, FpElem,
+    GenericUInt,
+};
+use thiserror::Error;
+
+#[derive(Error, Debug, PartialEq, Clone)]
+pub enum EncodingError {
+    #[error("input ended early")]
+    Truncated,
+    #[error("{0} bytes left over after the encoding")]
+    TrailingBytes(usize),
+    #[error("expected tag {expected:#04x}, found {found:#04x}")]
+    UnexpectedTag { expected: u8, found: u8 },
+    #[error("length is not minimally encoded")]
+    NonMinimalLength,
+    #[error("integer is not minimally encoded")]
+    NonMinimalInteger,
+    #[error("integer is negative")]
+    NegativeInteger,
+    #[error("integer is not below the group order")]
+    IntegerOutOfRange,
+    #[error("expected {expected} bytes, found {found}")]
+    WrongLength { expected: usize, found: usize },
+    #[error("{0:#04x} is not a SEC1 point encoding tag")]
+    InvalidPointTag(u8),
+    #[error("coordinate is not below the field prime")]
+    CoordinateOutOfRange,
+    #[error("point is not on the curve")]
+    NotOnCurve,
+}
+
+const DER_INTEGER: u8 = 0x02;
+const DER_SEQUENCE: u8 = 0x30;
+
+// bytes needed to hold any value mod n
+pub(crate) fn scalar_len<T: GenericUInt>(order: T) -> usize {
+    count_bits_generic_uint(order).div_ceil(8) as usize
+}
+
+fn der_length(len: usize) -> Vec<u8> {
+    if len < 0x80 {
+        return vec![len as u8];
+    }
+    let bytes: Vec<u8> = len
+        .to_be_bytes()
+        .into_iter()
+        .skip_while(|byte| *byte == 0)
+        .collect();
+    let mut out = vec![0x80 | bytes.len() as u8];
+    out.extend(bytes);
+    out
+}
+
+// minimal two's complement - strip leading zeros, then put one back if the top bit is set
+fn der_integer<T: GenericUInt>(x: T, len: usize) -> Vec<u8> {
+    let bytes = generic_uint_to_be_bytes(x, len);
+    let mut content: Vec<u8> = bytes.into_iter().skip_while(|byte| *byte == 0).collect();
+    if content.is_empty() || content[0] & 0x80 != 0 {
+        content.insert(0, 0x00);
+    }
+    let mut out = vec![DER_INTEGER];
+    out.extend(der_length(content.len()));
+    out.extend(content);
+    out
+}
+
+// Strict DER reader, anything BER would allow but DER doesn't is an error
+struct DerReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> DerReader<'a> {
+    fn byte(&mut self) -> Result<u8, EncodingError> {
+        let (first, rest) = self.bytes.split_first().ok_or(EncodingError::Truncated)?;
+        self.bytes = rest;
+        Ok(*first)
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], EncodingError> {
+        if self.bytes.len() < len {
+            return Err(EncodingError::Truncated);
+        }
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn length(&mut self) -> Result<usize, EncodingError> {
+        let first = self.byte()?;
+        if first < 0x80 {
+            return Ok(first as usize);
+        }
+        let count = (first & 0x7f) as usize;
+        // 0x80 is BER's indefinite length, and more than a usize can't be a real length here
+        if count == 0 || count > std::mem::size_of::<usize>() {
+            return Err(EncodingError::NonMinimalLength);
+        }
+        let bytes = self.take(count)?;
+        if bytes[0] == 0 {
+            return Err(EncodingError::NonMinimalLength);
+        }
+        let len = bytes
+            .iter()
+            .fold(0usize, |acc, byte| (acc << 8) | *byte as usize);
+        if len < 0x80 {
+            return Err(EncodingError::NonMinimalLength);
+        }
+        Ok(len)
+    }
+
+    fn tagged(&mut self, tag: u8) -> Result<&'a [u8], EncodingError> {
+        let found = self.byte()?;
+        if found != tag {
+            return Err(EncodingError::UnexpectedTag {
+                expected: tag,
+                found,
+            });
+        }
+        let len = self.length()?;
+        self.take(len)
+    }
+
+    // a non negative INTEGER below order
+    fn integer<T: GenericUInt>(&mut self, order: T) -> Result<T, EncodingError> {
+        let content = self.tagged(DER_INTEGER)?;
+        match content {
+            [] => return Err(EncodingError::Truncated),
+            [first, ..] if first & 0x80 != 0 => return Err(EncodingError::NegativeInteger),
+            [0x00, second, ..] if second & 0x80 == 0 => {
+                return Err(EncodingError::NonMinimalInteger)
+            }
+            _ => (),
+        }
+        let content: Vec<u8> = content.iter().copied().skip_while(|b| *b == 0).collect();
+        // check the width before building a T so nothing can overflow
+        if content.len() > scalar_len(order) {
+            return Err(EncodingError::IntegerOutOfRange);
+        }
+        let x: T = generic_uint_from_be_bytes(&content);
+        if x >= order {
+            return Err(EncodingError::IntegerOutOfRange);
+        }
+        Ok(x)
+    }
+
+    fn finish(&self) -> Result<(), EncodingError> {
+        if self.bytes.is_empty() {
+            Ok(())
+        } else {
+            Err(EncodingError::TrailingBytes(self.bytes.len()))
+        }
+    }
+}
+
+// Wire formats for signatures, both need n to know how wide r and s are
+impl<T: GenericUInt> Signature<FpElem<T>> {
+    // X9.62 Ecdsa-Sig-Value, SEQUENCE { r INTEGER, s INTEGER }
+    pub fn to_der(&self) -> Vec<u8> {
+        let len = scalar_len(self.targetx.prime);
+        let mut body = der_integer(self.targetx.number, len);
+        body.extend(der_integer(self.sig.number, len));
+        let mut out = vec![DER_SEQUENCE];
+        out.extend(der_length(body.len()));
+        out.extend(body);
+        out
+    }
+
+    // rejects anything that isn't exactly what to_der would have written, and r or s >= n
+    pub fn from_der(bytes: &[u8], order: T) -> Result<Self, EncodingError> {
+        let mut outer = DerReader { bytes };
+        let mut seq = DerReader {
+            bytes: outer.tagged(DER_SEQUENCE)?,
+        };
+        outer.finish()?;
+        let r = seq.integer(order)?;
+        let s = seq.integer(order)?;
+        seq.finish()?;
+        Ok(Signature::new(FpElem::new(r, order), FpElem::new(s, order)))
+    }
+
+    // IEEE P1363 r || s, each left padded to the byte length of n, as used by JWS and WebAuthn
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let len = scalar_len(self.targetx.prime);
+        let mut out = generic_uint_to_be_bytes(self.targetx.number, len);
+        out.extend(generic_uint_to_be_bytes(self.sig.number, len));
+        out
+    }
+
+    pub fn from_bytes(bytes: &[u8], order: T) -> Result<Self, EncodingError> {
+        let len = scalar_len(order);
+        if bytes.len() != 2 * len {
+            return Err(EncodingError::WrongLength {
+                expected: 2 * len,
+                found: bytes.len(),
+            });
+        }
+        let (r, s) = bytes.split_at(len);
+        let (r, s): (T, T) = (generic_uint_from_be_bytes(r), generic_uint_from_be_bytes(s));
+        if r >= order || s >= order {
+            return Err(EncodingError::IntegerOutOfRange);
+        }
+        Ok(Signature::new(FpElem::new(r, order), FpElem::new(s, order)))
+    }
+}
+
+const SEC1_INFINITY: u8 = 0x00;
+const SEC1_EVEN: u8 = 0x02;
+const SEC1_ODD: u8 = 0x03;
+const SEC1_UNCOMPRESSED: u8 = 0x04;
+
+// SEC1 2.3.3/2.3.4 point encodings, coordinates are left padded to the byte length of p
+impl<T: GenericUInt> ECPoint<FpElem<T>> {
+    // 0x02/0x03 || x when compressed (the tag carries the parity of y), 0x04 || x || y when not,
+    // and a lone 0x00 for infinity either way
+    pub fn to_sec1(&self, compressed: bool) -> Vec<u8> {
+        let Some((x, y)) = self.position else {
+            return vec![SEC1_INFINITY];
+        };
+        let len = scalar_len(x.prime);
+        let odd = y.number & T::from(1) != T::from(0);
+        let mut out = if compressed {
+            vec![if odd { SEC1_ODD } else { SEC1_EVEN }]
+        } else {
+            vec![SEC1_UNCOMPRESSED]
+        };
+        out.extend(generic_uint_to_be_bytes(x.number, len));
+        if !compressed {
+            out.extend(generic_uint_to_be_bytes(y.number, len));
+        }
+        out
+    }
+
+    // takes the curve the same way new_infinity does, and only ever hands back points on it
+    pub fn from_sec1(
+        bytes: &[u8],
+        a: impl Into<T>,
+        b: impl Into<T>,
+        prime: impl Into<T>,
+    ) -> Result<Self, EncodingError> {
+        let (a, b, prime) = (a.into(), b.into(), prime.into());
+        let len = scalar_len(prime);
+        let (&tag, rest) = bytes.split_first().ok_or(EncodingError::Truncated)?;
+        let expected = match tag {
+            SEC1_INFINITY => 0,
+            SEC1_EVEN | SEC1_ODD => len,
+            SEC1_UNCOMPRESSED => 2 * len,
+            _ => return Err(EncodingError::InvalidPointTag(tag)),
+        };
+        if rest.len() != expected {
+            return Err(EncodingError::WrongLength {
+                expected: expected + 1,
+                found: bytes.len(),
+            });
+        }
+        // width is fixed by the check above so these can't overflow T
+        let coordinate = |bytes: &[u8]| -> Result<T, EncodingError> {
+            let c: T = generic_uint_from_be_bytes(bytes);
+            if c >= prime {
+                return Err(EncodingError::CoordinateOutOfRange);
+            }
+            Ok(c)
+        };
+        match tag {
+            SEC1_INFINITY => Ok(ECPoint::new_infinity(a, b, prime)),
+            SEC1_EVEN | SEC1_ODD => {
+                let x = coordinate(rest)?;
+                // from_x also turns down 0x03 for y = 0, which only has the one encoding
+                ECPoint::from_x(x, tag == SEC1_ODD, a, b, prime).ok_or(EncodingError::NotOnCurve)
+            }
+            _ => {
+                let (x, y) = rest.split_at(len);
+                let point = ECPoint::new(coordinate(x)?, coordinate(y)?, a, b, prime);
+                if !point.is_on_curve() {
+                    return Err(EncodingError::NotOnCurve);
+                }
+                Ok(point)
+            }
+        }
+    }
+}
@@ -0,0 +1,134 @@
+use crate::ellipticcurve::ECPoint;
+use crate::fpelem::{count_bits_generic_uint, select, FpElem, GenericUInt};
+use crate::montgomery::{MontFpElem, MontgomeryParams};
+use crate::projective::ProjectivePoint;
+use std::fmt;
+use std::fmt::Debug;
+
+// bits per window, so 2^FIXED_BASE_WINDOW points are stored for each one
+const FIXED_BASE_WINDOW: u64 = 4;
+
+// Precomputed j * 16^i * P for every window i of a scalar mod the order and every digit j, so
+// multiplying P by a secret is one table lookup and one complete addition per 4 bits, with no
+// doublings. Built once per base point - CurveDomain keeps one for its generator.
+// The entries are kept in Montgomery form and the additions done on MontFpElem, and like
+// mul_secret every entry of a window is read the same way and the loop always runs over the bit
+// length of the order, so it's constant time as far as T's own arithmetic is. When P has even
+// order the complete additions can hit a difference of order 2 and it falls back to the Jacobian
+// multiplication the same way.
+#[derive(Clone)]
+pub struct FixedBaseTable<T> {
+    base: ECPoint<FpElem<T>>,
+    order: T,
+    params: MontgomeryParams<T>,
+    // the Montgomery form (X, Y, Z) of each entry
+    windows: Vec<Vec<[T; 3]>>,
+}
+
+// the table itself is a lot of numbers to print
+impl<T: GenericUInt + Debug> Debug for FixedBaseTable<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FixedBaseTable")
+            .field("base", &self.base)
+            .field("order", &self.order)
+            .field("windows", &self.windows.len())
+            .finish()
+    }
+}
+
+impl<T: GenericUInt> FixedBaseTable<T> {
+    // order is the order of base, and the modulus of the scalars passed to mul_secret
+    pub fn new(base: &ECPoint<FpElem<T>>, order: T) -> Self {
+        let params = MontgomeryParams::new(base.curve().0.prime);
+        let infinity = Self::infinity(base, &params);
+        let count = count_bits_generic_uint(order).div_ceil(FIXED_BASE_WINDOW);
+        let mut windows = Vec::with_capacity(count as usize);
+        // 16^i * P
+        let mut step = ProjectivePoint::from_affine(&base.to_montgomery(&params));
+        for _ in 0..count {
+            let mut window = vec![infinity, step];
+            for j in 2..1usize << FIXED_BASE_WINDOW {
+                window.push(window[j - 1] + step);
+            }
+            for _ in 0..FIXED_BASE_WINDOW {
+                step = step.double();
+            }
+            windows.push(
+                window
+                    .iter()
+                    .map(|entry| entry.coordinates().map(|c| c.mont()))
+                    .collect(),
+            );
+        }
+        FixedBaseTable {
+            base: *base,
+            order,
+            params,
+            windows,
+        }
+    }
+
+    pub fn base(&self) -> &ECPoint<FpElem<T>> {
+        &self.base
+    }
+
+    pub fn order(&self) -> T {
+        self.order
+    }
+
+    // infinity on base's curve, in Montgomery form
+    fn infinity<'p>(
+        base: &ECPoint<FpElem<T>>,
+        params: &'p MontgomeryParams<T>,
+    ) -> ProjectivePoint<MontFpElem<'p, T>> {
+        let (a, b) = base.to_montgomery(params).curve();
+        ProjectivePoint::from_affine(&ECPoint::from_parts(None, a, b))
+    }
+
+    // window[digit], reading every entry the same way - each is picked with a choice that's 1 for
+    // the entry asked for and 0 for the rest, worked out by arithmetic from digit's bits (each 0
+    // or 1, lowest first)
+    fn lookup(window: &[[T; 3]], digit: &[T]) -> [T; 3] {
+        let one = T::from(1);
+        let mut out = [T::from(0); 3];
+        for (i, entry) in window.iter().enumerate() {
+            let choice = digit.iter().enumerate().fold(one, |choice, (bit, d)| {
+                if (i >> bit) & 1 == 1 {
+                    choice * *d
+                } else {
+                    choice * (one - *d)
+                }
+            });
+            for (out, coordinate) in out.iter_mut().zip(entry) {
+                *out = select(*out, *coordinate, choice);
+            }
+        }
+        out
+    }
+
+    // k * base for a secret k mod the order, see mul_secret on ECPoint
+    pub fn mul_secret(&self, k: &FpElem<T>) -> ECPoint<FpElem<T>> {
+        assert!(
+            k.prime == self.order,
+            "scalar must be mod the table's order"
+        );
+        let one = T::from(1);
+        let params = &self.params;
+        let infinity = Self::infinity(&self.base, params);
+        let mut rest = k.number;
+        let mut result = infinity;
+        for window in self.windows.iter() {
+            let mut digit = [T::from(0); FIXED_BASE_WINDOW as usize];
+            for bit in digit.iter_mut() {
+                *bit = rest & one;
+                rest = rest >> one;
+            }
+            let entry = Self::lookup(window, &digit).map(|c| MontFpElem::from_mont(c, params));
+            result = result + infinity.with_coordinates(entry);
+        }
+        if result.is_degenerate() {
+            return self.base * k.number;
+        }
+        ECPoint::from(result.to_affine())
+    }
+}
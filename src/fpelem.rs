@@ -1,5 +1,10 @@
+use rand::{CryptoRng, RngCore};
 use std::fmt::{Debug, Display, Formatter};
-use std::ops::{Add, BitAnd, Div, Mul, Rem, Shl, Shr, Sub};
+use std::iter::Sum;
+use std::ops::{
+    Add, AddAssign, BitAnd, Div, DivAssign, Mul, MulAssign, Neg, Rem, Shl, Shr, Sub, SubAssign,
+};
+use thiserror::Error;
 //extern crate primitive_types;
 //use primitive_types::U512;
 
@@ -39,9 +44,9 @@ impl<T> GenericUInt for T where
 fn sub_mod<T: GenericUInt>(a: T, b: T, m: T) -> T {
     let (a, b) = (a % m, b % m);
     if a >= b {
-        return a - b;
+        a - b
     } else {
-        return m - b + a;
+        m - b + a
     }
 }
 
@@ -55,9 +60,26 @@ fn add_mod<T: GenericUInt>(a: T, b: T, m: T) -> T {
     }
 }
 
+// whether any product of two values below m fits in T, which it does when m is in the bottom half
+// of T's bits. The shift is done 128 bits at a time as shifts take a T made from a u8
+fn products_fit<T: GenericUInt>(m: T) -> bool {
+    let mut rest = type_bits::<T>() / 2;
+    let mut high = m;
+    while rest > 0 {
+        let step = rest.min(128);
+        high = high >> T::from(step as u8);
+        rest -= step;
+    }
+    high == T::from(0)
+}
+
+fn mul_mod<T: GenericUInt>(a: T, b: T, m: T) -> T {
+    mul_mod_with(a, b, m, products_fit(m))
+}
+
 // Treat multiplication as composition of addition and spam binary expansion
 // See UofCambridge Quantum Information and Computation sheet 3 lol
-fn mul_mod<T: GenericUInt>(a: T, b: T, m: T) -> T {
+fn mul_mod_by_doubling<T: GenericUInt>(a: T, b: T, m: T) -> T {
     let mut b = b;
     let zero = T::from(0);
     let mut result = zero;
@@ -73,8 +95,23 @@ fn mul_mod<T: GenericUInt>(a: T, b: T, m: T) -> T {
     result
 }
 
+// that's hundreds of add_mods though, so when T is at least twice as wide as m (U512 for a 256 bit
+// curve, u64 for toy ones) just multiply and reduce. fits is products_fit(m), which pow_mod works
+// out once for all its products
+fn mul_mod_with<T: GenericUInt>(a: T, b: T, m: T, fits: bool) -> T {
+    if fits {
+        ((a % m) * (b % m)) % m
+    } else {
+        mul_mod_by_doubling(a, b, m)
+    }
+}
+
 // again spam binary expansion - quite slow as mul_mod does the same
 pub fn pow_mod<T: GenericUInt>(a: T, e: T, m: T) -> T {
+    pow_mod_with(a, e, m, products_fit(m))
+}
+
+fn pow_mod_with<T: GenericUInt>(a: T, e: T, m: T, fits: bool) -> T {
     let mut b = e;
     let zero = T::from(0);
     let one = T::from(1);
@@ -83,14 +120,103 @@ pub fn pow_mod<T: GenericUInt>(a: T, e: T, m: T) -> T {
     while b != zero {
         if (b & T::from(1)) != zero {
             // note that the overflow safe property hinges on add_mod being overflow safe
-            result = mul_mod(result, current, m);
+            result = mul_mod_with(result, current, m, fits);
         }
-        current = mul_mod(current, current, m);
+        current = mul_mod_with(current, current, m, fits);
         b = b >> T::from(1);
     }
     result
 }
 
+// big endian bytes -> T, one byte at a time so this works for anything implementing GenericUInt
+// bytes that don't fit in T are shifted out the top, so callers should check the width themselves
+pub fn generic_uint_from_be_bytes<T: GenericUInt>(bytes: &[u8]) -> T {
+    let mut out = T::from(0);
+    for byte in bytes {
+        out = (out << T::from(8)) + T::from(*byte);
+    }
+    out
+}
+
+// T -> big endian bytes, left padded with zeros (or truncated from the top) to exactly len bytes
+pub fn generic_uint_to_be_bytes<T: GenericUInt>(x: T, len: usize) -> Vec<u8> {
+    let zero = T::from(0);
+    let one = T::from(1);
+    let mut x = x;
+    let mut out = vec![0u8; len];
+    for byte in out.iter_mut().rev() {
+        let low = x & T::from(255);
+        for bit in 0..8 {
+            if (low >> T::from(bit)) & one != zero {
+                *byte |= 1 << bit;
+            }
+        }
+        x = x >> T::from(8);
+    }
+    out
+}
+
+pub(crate) fn count_bits_generic_uint<T: GenericUInt>(x: T) -> u64 {
+    let one = T::from(1);
+    let mut count = 0;
+    let mut upperbound = T::from(0);
+    loop {
+        upperbound = upperbound << one;
+        upperbound = upperbound + one;
+        count += 1;
+        if upperbound >= x {
+            return count;
+        }
+    }
+}
+
+// number of bits in T - GenericUInt types are fixed width integers with nothing else in them, so
+// that's just their size
+pub(crate) fn type_bits<T: GenericUInt>() -> u64 {
+    8 * std::mem::size_of::<T>() as u64
+}
+
+// generate n random bits where n is minimal bits to represent "under" (in the canonical binary rep)
+// if number generated is bigger, sack this run and try again - rejection sampling - however 0.5 prob of success each time, range is NON INCLUSIVE
+// the bits come out of the rng a whole buffer at a time via fill_bytes (so a word per next_u64), not one call per bit
+pub(crate) fn get_generic_uint_below<T: GenericUInt, R: RngCore + ?Sized>(
+    under: T,
+    rng: &mut R,
+) -> T {
+    assert!(under > T::from(0), "nothing is below 0");
+    let bits = count_bits_generic_uint(under - T::from(1));
+    let nbytes = bits.div_ceil(8);
+    let mut buf = vec![0u8; nbytes as usize];
+    // chop the leading byte down so every candidate has at most n bits
+    let topmask = 0xffu8 >> (8 * nbytes - bits);
+    // loop will exit with probability 1
+    loop {
+        rng.fill_bytes(&mut buf);
+        buf[0] &= topmask;
+        let nextout: T = generic_uint_from_be_bytes(&buf);
+        // if the solution is bigger than our desired bound, sample again
+        if nextout < under {
+            return nextout;
+        }
+    }
+}
+
+// uniform in [1, under-1], which for under = n is exactly the range a nonce or private key can take
+pub(crate) fn get_nonzero_generic_uint_below<T: GenericUInt, R: RngCore + ?Sized>(
+    under: T,
+    rng: &mut R,
+) -> T {
+    let one = T::from(1);
+    assert!(under > one, "nothing nonzero is below 1");
+    get_generic_uint_below(under - one, rng) + one
+}
+
+// a when choice is 0 and b when it's 1, by arithmetic rather than a branch so which one was
+// picked doesn't show in the timing - as far as T's own + and * don't
+pub(crate) fn select<T: GenericUInt>(a: T, b: T, choice: T) -> T {
+    (T::from(1) - choice) * a + choice * b
+}
+
 // this is so bad it's funny, case bashes a signed integer type
 #[derive(Clone, Copy)]
 struct GenSignedUint<T> {
@@ -98,7 +224,7 @@ struct GenSignedUint<T> {
     isneg: bool,
 }
 
-fn mul_inv<T: GenericUInt>(a: T, b: T) -> T {
+pub(crate) fn mul_inv<T: GenericUInt>(a: T, b: T) -> T {
     let (mut a, mut b) = (a, b);
     if b <= T::from(1) {
         return T::from(0);
@@ -143,9 +269,9 @@ fn mul_inv<T: GenericUInt>(a: T, b: T) -> T {
         x1 = t2;
     }
     if x1.isneg {
-        return b0 - x1.value;
+        b0 - x1.value
     } else {
-        return x1.value;
+        x1.value
     }
 }
 
@@ -154,6 +280,42 @@ fn mul_inv<T: GenericUInt>(a: T, b: T) -> T {
 //    pow_mod(a, m - T::from(2), m)
 //}
 
+// what the checked_ ops on FpElem and ECPoint give back instead of panicking
+#[derive(Error, Debug, PartialEq, Clone)]
+pub enum ArithmeticError {
+    #[error("elements are mod different primes")]
+    ModulusMismatch,
+    #[error("element has no inverse mod p")]
+    NotInvertible,
+    #[error("points are on different curves")]
+    CurveMismatch,
+}
+
+// what the point arithmetic needs from a field element, so ECPoint and JacobianPoint run on FpElem
+// and MontFpElem alike
+pub trait FieldElement:
+    Copy
+    + PartialEq
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + Div<Output = Self>
+    + Neg<Output = Self>
+{
+    type Int: GenericUInt;
+    // the element of the same field as self with this value
+    fn with_number(&self, number: Self::Int) -> Self;
+    // the value in [0, p)
+    fn to_int(&self) -> Self::Int;
+    fn modulus(&self) -> Self::Int;
+    fn try_inverse(&self) -> Result<Self, ArithmeticError>;
+    // self when choice is 0 and other when it's 1, without branching on choice
+    fn select(&self, other: &Self, choice: Self::Int) -> Self;
+    fn is_zero(&self) -> bool {
+        self.to_int() == Self::Int::from(0)
+    }
+}
+
 // An element of the unique finite field of order p where p is prime
 // No checks are done for primality of p as this is expensive, however if p were to be a composite
 // number everything should work other than the Div trait (which will break even if m if invertible
@@ -171,9 +333,159 @@ impl<T: GenericUInt> FpElem<T> {
     }
 
     pub fn new_from(number: impl Into<T>, prime: impl Into<T>) -> Self {
-        let (number, prime) = (number.into(), prime.into());
-        let number = number % prime;
-        FpElem { number, prime }
+        FpElem::new(number.into(), prime.into())
+    }
+
+    // uniform over the whole field
+    pub fn random<R: RngCore + CryptoRng + ?Sized>(prime: T, rng: &mut R) -> Self {
+        FpElem::new(get_generic_uint_below(prime, rng), prime)
+    }
+
+    // uniform over the nonzero elements, i.e. the units of the field
+    pub fn random_nonzero<R: RngCore + CryptoRng + ?Sized>(prime: T, rng: &mut R) -> Self {
+        FpElem::new(get_nonzero_generic_uint_below(prime, rng), prime)
+    }
+
+    // Legendre symbol (a/p) by Euler's criterion: 1 for a nonzero square, -1 for a non square, 0 for 0
+    pub fn legendre(&self) -> i8 {
+        let zero = T::from(0);
+        let one = T::from(1);
+        let p = self.prime;
+        if self.number == zero {
+            return 0;
+        }
+        if p == T::from(2) || self.pow((p - one) / T::from(2)).number == one {
+            1
+        } else {
+            -1
+        }
+    }
+
+    // 0 counts, it's 0^2
+    pub fn is_square(&self) -> bool {
+        self.legendre() != -1
+    }
+
+    // None if this isn't a square mod p
+    // of the two roots this returns whichever falls out, negate for the other one
+    pub fn sqrt(&self) -> Option<Self> {
+        let zero = T::from(0);
+        let one = T::from(1);
+        let two = T::from(2);
+        let p = self.prime;
+        if self.number == zero || p == two {
+            return Some(*self);
+        }
+        let candidate = if p & T::from(3) == T::from(3) {
+            // p = 3 mod 4: a^((p+1)/4) squares to a^((p+1)/2) = a * (a/p)
+            self.pow((p + one) >> two)
+        } else if p & T::from(7) == T::from(5) {
+            // Atkin, p = 5 mod 8: with b = (2a)^((p-5)/8) and i = 2ab^2 (a square root of -1
+            // when a is a square), ab(i - 1) is a root
+            let twoa = self * two;
+            let b = twoa.pow((p - T::from(5)) >> T::from(3));
+            let i = twoa * b.pow(two);
+            self * b * (i - FpElem::new(one, p))
+        } else {
+            return self.sqrt_tonelli_shanks();
+        };
+        // the shortcuts hand back garbage for non squares rather than failing, so check
+        if candidate * candidate == *self {
+            Some(candidate)
+        } else {
+            None
+        }
+    }
+
+    // 1 / self, or NotInvertible for 0 (or anything sharing a factor with p when p isn't prime)
+    pub fn try_inverse(&self) -> Result<Self, ArithmeticError> {
+        let inverse = FpElem {
+            number: mul_inv(self.number, self.prime),
+            prime: self.prime,
+        };
+        // mul_inv hands back 0 or 1 when there's no inverse, so check rather than trust it
+        if (inverse * self).number != T::from(1) % self.prime {
+            return Err(ArithmeticError::NotInvertible);
+        }
+        Ok(inverse)
+    }
+
+    // None for no elements, as there's no p to take 0 mod. Like + this panics on mixed primes
+    pub fn try_sum<I: IntoIterator<Item = Self>>(elems: I) -> Option<Self> {
+        elems.into_iter().reduce(|sum, x| sum + x)
+    }
+
+    // the operators, but with errors rather than panics (or a silent 0 for Div)
+    pub fn checked_add(&self, other: &Self) -> Result<Self, ArithmeticError> {
+        self.same_prime(other)?;
+        Ok(self + other)
+    }
+
+    pub fn checked_sub(&self, other: &Self) -> Result<Self, ArithmeticError> {
+        self.same_prime(other)?;
+        Ok(self - other)
+    }
+
+    pub fn checked_mul(&self, other: &Self) -> Result<Self, ArithmeticError> {
+        self.same_prime(other)?;
+        Ok(self * other)
+    }
+
+    pub fn checked_div(&self, other: &Self) -> Result<Self, ArithmeticError> {
+        self.same_prime(other)?;
+        Ok(self * other.try_inverse()?)
+    }
+
+    fn same_prime(&self, other: &Self) -> Result<(), ArithmeticError> {
+        if self.prime != other.prime {
+            return Err(ArithmeticError::ModulusMismatch);
+        }
+        Ok(())
+    }
+
+    // general Tonelli-Shanks, what's left over is p = 1 mod 8
+    fn sqrt_tonelli_shanks(&self) -> Option<Self> {
+        let zero = T::from(0);
+        let one = T::from(1);
+        let two = T::from(2);
+        let p = self.prime;
+        let minus_one = p - one;
+        if !self.is_square() {
+            return None;
+        }
+        // p - 1 = q * 2^s with q odd
+        let (mut q, mut s) = (p - one, 0u64);
+        while q & one == zero {
+            q = q >> one;
+            s += 1;
+        }
+        // any non residue will do, half of everything is one so this is quick
+        let mut z = FpElem::new(two, p);
+        while z.pow((p - one) / two).number != minus_one {
+            z = FpElem::new(z.number + one, p);
+        }
+        let mut m = s;
+        let mut c = z.pow(q);
+        let mut t = self.pow(q);
+        let mut r = self.pow((q + one) / two);
+        while t.number != one {
+            // least i with t^(2^i) = 1, always < m
+            let mut i = 0;
+            let mut t2i = t;
+            while t2i.number != one {
+                t2i = t2i * t2i;
+                i += 1;
+            }
+            let mut b = c;
+            for _ in 0..(m - i - 1) {
+                b = b * b;
+            }
+            m = i;
+            c = b * b;
+            t *= c;
+            r *= b;
+        }
+        Some(r)
     }
 }
 
@@ -218,6 +530,7 @@ impl<T: GenericUInt> Mul for &FpElem<T> {
     }
 }
 
+#[allow(clippy::suspicious_arithmetic_impl)]
 impl<T: GenericUInt> Mul<T> for &FpElem<T> {
     type Output = FpElem<T>;
     fn mul(self, tomul: T) -> FpElem<T> {
@@ -228,6 +541,121 @@ impl<T: GenericUInt> Mul<T> for &FpElem<T> {
     }
 }
 
+// the owned and mixed reference forms of a binary op, and its assigning version, all going
+// through the &a op &b impl - MontFpElem uses this too. The impl's generics go in the brackets
+macro_rules! forward_binop {
+    ([$($gen:tt)*] $elem:ty, $imp:ident, $method:ident, $assign_imp:ident, $assign_method:ident) => {
+        impl<$($gen)*> $imp for $elem {
+            type Output = $elem;
+            fn $method(self, rhs: $elem) -> $elem {
+                (&self).$method(&rhs)
+            }
+        }
+
+        impl<$($gen)*> $imp<&$elem> for $elem {
+            type Output = $elem;
+            fn $method(self, rhs: &$elem) -> $elem {
+                (&self).$method(rhs)
+            }
+        }
+
+        impl<$($gen)*> $imp<$elem> for &$elem {
+            type Output = $elem;
+            fn $method(self, rhs: $elem) -> $elem {
+                self.$method(&rhs)
+            }
+        }
+
+        impl<$($gen)*> $assign_imp for $elem {
+            fn $assign_method(&mut self, rhs: $elem) {
+                *self = (&*self).$method(&rhs);
+            }
+        }
+
+        impl<$($gen)*> $assign_imp<&$elem> for $elem {
+            fn $assign_method(&mut self, rhs: &$elem) {
+                *self = (&*self).$method(rhs);
+            }
+        }
+    };
+}
+pub(crate) use forward_binop;
+
+forward_binop!([T: GenericUInt] FpElem<T>, Add, add, AddAssign, add_assign);
+forward_binop!([T: GenericUInt] FpElem<T>, Sub, sub, SubAssign, sub_assign);
+forward_binop!([T: GenericUInt] FpElem<T>, Mul, mul, MulAssign, mul_assign);
+forward_binop!([T: GenericUInt] FpElem<T>, Div, div, DivAssign, div_assign);
+
+impl<T: GenericUInt> Mul<T> for FpElem<T> {
+    type Output = FpElem<T>;
+    fn mul(self, tomul: T) -> FpElem<T> {
+        Mul::mul(&self, tomul)
+    }
+}
+
+impl<T: GenericUInt> MulAssign<T> for FpElem<T> {
+    fn mul_assign(&mut self, tomul: T) {
+        *self = Mul::mul(&*self, tomul);
+    }
+}
+
+// -a = p - a, with -0 = 0
+impl<T: GenericUInt> Neg for &FpElem<T> {
+    type Output = FpElem<T>;
+    fn neg(self) -> FpElem<T> {
+        let zero = FpElem {
+            number: T::from(0),
+            prime: self.prime,
+        };
+        Sub::sub(&zero, self)
+    }
+}
+
+impl<T: GenericUInt> Neg for FpElem<T> {
+    type Output = FpElem<T>;
+    fn neg(self) -> FpElem<T> {
+        -&self
+    }
+}
+
+// elems.sum::<Option<_>>(), which is try_sum - for no elements there's no p to take 0 mod
+impl<T: GenericUInt> Sum<FpElem<T>> for Option<FpElem<T>> {
+    fn sum<I: Iterator<Item = FpElem<T>>>(iter: I) -> Self {
+        FpElem::try_sum(iter)
+    }
+}
+
+impl<'a, T: GenericUInt> Sum<&'a FpElem<T>> for Option<FpElem<T>> {
+    fn sum<I: Iterator<Item = &'a FpElem<T>>>(iter: I) -> Self {
+        FpElem::try_sum(iter.copied())
+    }
+}
+
+impl<T: GenericUInt> FieldElement for FpElem<T> {
+    type Int = T;
+    fn with_number(&self, number: T) -> Self {
+        FpElem {
+            number: number % self.prime,
+            prime: self.prime,
+        }
+    }
+    fn to_int(&self) -> T {
+        self.number
+    }
+    fn modulus(&self) -> T {
+        self.prime
+    }
+    fn try_inverse(&self) -> Result<Self, ArithmeticError> {
+        FpElem::try_inverse(self)
+    }
+    fn select(&self, other: &Self, choice: T) -> Self {
+        FpElem {
+            number: select(self.number, other.number, choice),
+            prime: self.prime,
+        }
+    }
+}
+
 pub trait Pow<T> {
     fn pow(&self, exponent: T) -> Self;
 }
@@ -256,3 +684,110 @@ impl<T: GenericUInt + Display> Display for FpElem<T> {
         write!(f, "{}", self.number)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_count_bits_generic_uint() {
+        assert_eq!(count_bits_generic_uint(0u32), 1);
+        assert_eq!(count_bits_generic_uint(1u32), 1);
+        assert_eq!(count_bits_generic_uint(2u32), 2);
+        assert_eq!(count_bits_generic_uint(3u32), 2);
+        assert_eq!(count_bits_generic_uint(255u32), 8);
+    }
+
+    #[test]
+    fn test_get_generic_uint_below_edges() {
+        let mut rng = StdRng::seed_from_u64(0);
+        // nothing but 0 is below 1
+        assert_eq!(get_generic_uint_below(1u32, &mut rng), 0);
+        // bounds either side of a byte boundary, where the top byte mask matters
+        for under in [2u32, 255, 256, 257, 65535, 65536] {
+            let mut seen_top = false;
+            for _ in 0..2000 {
+                let result = get_generic_uint_below(under, &mut rng);
+                assert!(result < under);
+                seen_top |= result >= under / 2;
+            }
+            assert!(seen_top);
+        }
+        for _ in 0..1000 {
+            let result = get_nonzero_generic_uint_below(3u64, &mut rng);
+            assert!(result == 1 || result == 2);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "nothing nonzero is below 1")]
+    fn test_get_nonzero_generic_uint_below_one() {
+        get_nonzero_generic_uint_below(1u32, &mut StdRng::seed_from_u64(0));
+    }
+
+    #[test]
+    fn test_get_generic_uint_below() {
+        let under = 30u32;
+        let mut rng = StdRng::seed_from_u64(30);
+        for _ in 0..100000 {
+            let result = get_generic_uint_below(under, &mut rng);
+            assert!(result < under);
+            // Check the distribution is approximately uniform
+        }
+        let mut counts = vec![0; under as usize];
+        for _ in 0..100000 {
+            let result = get_generic_uint_below(under, &mut rng);
+            assert!(result < under);
+            counts[result as usize] += 1;
+        }
+        let expected_count = 100000 / under;
+        let tolerance = expected_count / 10; // 10% tolerance
+        assert!((counts[4] as i32 - expected_count as i32).abs() <= tolerance as i32);
+        //
+        // // Plot the distribution
+        // use plotters::prelude::*;
+        //
+        // let root = BitMapBackend::new("random_distribution.png", (640, 480))
+        //     .into_drawing_area();
+        // root.fill(&WHITE).unwrap();
+        //
+        // let max_count = *counts.iter().max().unwrap() as f32;
+        // let mut chart = ChartBuilder::on(&root)
+        //     .caption("Distribution of Random Values", ("sans-serif", 30))
+        //     .margin(5)
+        //     .x_label_area_size(30)
+        //     .y_label_area_size(30)
+        //     .build_cartesian_2d(0..under as i32, 0f32..max_count)
+        //     .unwrap();
+        //
+        // chart.configure_mesh().draw().unwrap();
+        //
+        // // Plot actual distribution
+        // chart
+        //     .draw_series(
+        //         Histogram::vertical(&chart)
+        //             .style(BLUE.mix(0.5).filled())
+        //             .data(counts.iter().enumerate().map(|(x, y)| (x as i32, *y as f32)))
+        //     )
+        //     .unwrap()
+        //     .label("Actual Distribution");
+        //
+        // // Plot expected line
+        // chart
+        //     .draw_series(LineSeries::new(
+        //         (0..under as i32).map(|x| (x, expected_count as f32)),
+        //         &RED,
+        //     ))
+        //     .unwrap()
+        //     .label("Expected Value");
+        //
+        // chart
+        //     .configure_series_labels()
+        //     .background_style(&WHITE.mix(0.8))
+        //     .border_style(&BLACK)
+        //     .draw()
+        //     .unwrap();
+    }
+}
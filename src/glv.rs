@@ -0,0 +1,183 @@
+use crate::curves::CurveDomain;
+use crate::ellipticcurve::{CurveError, ECPoint};
+use crate::fpelem::{count_bits_generic_uint, type_bits, FpElem, GenericUInt};
+use crate::validation::isqrt;
+
+// an integer as its size and sign, with just the arithmetic the lattice work below needs
+#[derive(Debug, PartialEq, Clone, Copy)]
+struct Signed<T> {
+    mag: T,
+    neg: bool,
+}
+
+impl<T: GenericUInt> Signed<T> {
+    fn new(mag: T, neg: bool) -> Self {
+        Signed {
+            mag,
+            neg: neg && mag != T::from(0),
+        }
+    }
+
+    fn negate(self) -> Self {
+        Signed::new(self.mag, !self.neg)
+    }
+
+    fn add(self, other: Self) -> Self {
+        if self.neg == other.neg {
+            Signed::new(self.mag + other.mag, self.neg)
+        } else if self.mag >= other.mag {
+            Signed::new(self.mag - other.mag, self.neg)
+        } else {
+            Signed::new(other.mag - self.mag, other.neg)
+        }
+    }
+
+    fn sub(self, other: Self) -> Self {
+        self.add(other.negate())
+    }
+
+    fn mul(self, other: Self) -> Self {
+        Signed::new(self.mag * other.mag, self.neg != other.neg)
+    }
+
+    // self / n rounded to the nearest integer, n > 0
+    fn round_div(self, n: T) -> Self {
+        Signed::new((self.mag + (n >> T::from(1))) / n, self.neg)
+    }
+}
+
+// The GLV endomorphism of an a = 0 curve, (x, y) -> (beta x, y), which acts on the points of
+// order n as multiplication by lambda - beta and lambda are nontrivial cube roots of 1 mod p and
+// mod n. Along with them goes a short basis of the lattice of (x, y) with x + y lambda = 0 mod n,
+// found by the extended Euclid method of Gallant, Lambert and Vanstone, for splitting a scalar k
+// into k1 + k2 lambda with k1 and k2 about half the size of n.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Endomorphism<T> {
+    pub beta: T,
+    pub lambda: T,
+    n: T,
+    v1: (Signed<T>, Signed<T>),
+    v2: (Signed<T>, Signed<T>),
+    // T has room for the products of a scalar with the basis
+    fits: bool,
+}
+
+impl<T: GenericUInt> Endomorphism<T> {
+    // no checks on beta and lambda here, which is why it's only for CurveDomain - from outside
+    // the crate the way in is CurveDomain::with_endomorphism, which checks them
+    pub(crate) fn new(beta: T, lambda: T, n: T) -> Self {
+        let zero = T::from(0);
+        // r_i = s_i n + t_i lambda, run until r hits 0
+        let mut r = vec![n, lambda];
+        let mut t = vec![Signed::new(zero, false), Signed::new(T::from(1), false)];
+        while r[r.len() - 1] != zero {
+            let i = r.len() - 1;
+            let q = r[i - 1] / r[i];
+            r.push(r[i - 1] - q * r[i]);
+            t.push(t[i - 1].sub(Signed::new(q, false).mul(t[i])));
+        }
+        // l is the last index with r_l >= sqrt(n), and v2 is the shorter of the two candidates
+        let root = isqrt(n);
+        let l = (0..r.len()).rev().find(|i| r[*i] > root).unwrap_or(0);
+        let vector = |i: usize| (Signed::new(r[i], false), t[i].negate());
+        let bits = |i: usize| count_bits_generic_uint(r[i]).max(count_bits_generic_uint(t[i].mag));
+        let width = type_bits::<T>();
+        // comparing squared lengths needs room for them, without it GLV is off anyway
+        let mut fits = l + 2 < r.len() && 2 * bits(l).max(bits(l + 2)) + 1 < width;
+        let norm = |i: usize| r[i] * r[i] + t[i].mag * t[i].mag;
+        let v1 = vector(l + 1);
+        let v2 = if !fits || norm(l) <= norm(l + 2) {
+            vector(l)
+        } else {
+            vector(l + 2)
+        };
+        let widest = [v1.0, v1.1, v2.0, v2.1]
+            .iter()
+            .map(|c| count_bits_generic_uint(c.mag))
+            .max()
+            .unwrap_or(0);
+        fits = fits && count_bits_generic_uint(n) + widest + 2 < width;
+        Endomorphism {
+            beta,
+            lambda,
+            n,
+            v1,
+            v2,
+            fits,
+        }
+    }
+
+    // (beta x, y), i.e. lambda * point for a point of order n
+    pub fn apply(&self, point: &ECPoint<FpElem<T>>) -> ECPoint<FpElem<T>> {
+        let (a, b) = point.curve();
+        match point.position {
+            Some((x, y)) => {
+                let x = FpElem::new(self.beta, a.prime) * x;
+                ECPoint::new(x.number, y.number, a.number, b.number, a.prime)
+            }
+            None => *point,
+        }
+    }
+
+    // k = k1 + k2 lambda mod n with k1 and k2 roughly sqrt(n) in size, each as (size, negative).
+    // None if T is too narrow for the products this needs
+    pub fn decompose(&self, k: T) -> Option<((T, bool), (T, bool))> {
+        if !self.fits {
+            return None;
+        }
+        let k = Signed::new(k % self.n, false);
+        let ((a1, b1), (a2, b2)) = (self.v1, self.v2);
+        let c1 = b2.mul(k).round_div(self.n);
+        let c2 = b1.negate().mul(k).round_div(self.n);
+        let k1 = k.sub(c1.mul(a1)).sub(c2.mul(a2));
+        let k2 = c1.mul(b1).add(c2.mul(b2)).negate();
+        Some(((k1.mag, k1.neg), (k2.mag, k2.neg)))
+    }
+}
+
+impl<T: GenericUInt> CurveDomain<T> {
+    // Attach the endomorphism with these beta and lambda, after checking that the curve has a = 0
+    // and cofactor 1 (so every point has order n and the map really is multiplication by
+    // lambda), that lambda is a cube root of 1 mod n, and that the pair agree on the generator.
+    pub fn with_endomorphism(self, beta: T, lambda: T) -> Result<Self, CurveError> {
+        let one = T::from(1);
+        let cube_root = |x: T, m: T| {
+            let x = FpElem::new(x, m);
+            x.number != one && (x * x + x).number == m - one
+        };
+        let endomorphism = Endomorphism::new(beta, lambda, self.n());
+        if self.a != T::from(0)
+            || self.cofactor != one
+            || !cube_root(beta, self.p)
+            || !cube_root(lambda, self.n())
+            || endomorphism.apply(&self.generator()) != self.generator() * lambda
+        {
+            return Err(CurveError::InvalidEndomorphism);
+        }
+        let mut domain = self;
+        domain.endomorphism = Some(endomorphism);
+        Ok(domain)
+    }
+
+    // sum of k_i P_i as ECPoint::multi_mul, with each term split in two by the endomorphism when
+    // the domain has one, which halves the doublings. The points have to be on the domain's curve
+    pub fn multi_mul(&self, terms: &[(ECPoint<FpElem<T>>, T)]) -> ECPoint<FpElem<T>> {
+        let endomorphism = match self.endomorphism {
+            Some(endomorphism) if endomorphism.fits => endomorphism,
+            _ => return ECPoint::multi_mul(terms),
+        };
+        let signed = |point: ECPoint<FpElem<T>>, neg: bool| if neg { -point } else { point };
+        let mut split = Vec::with_capacity(2 * terms.len());
+        for (point, k) in terms.iter() {
+            let ((k1, neg1), (k2, neg2)) = endomorphism.decompose(*k).unwrap();
+            split.push((signed(*point, neg1), k1));
+            split.push((signed(endomorphism.apply(point), neg2), k2));
+        }
+        ECPoint::multi_mul(&split)
+    }
+
+    // k * point, through multi_mul so it gets the endomorphism where there is one
+    pub fn mul(&self, point: &ECPoint<FpElem<T>>, k: T) -> ECPoint<FpElem<T>> {
+        self.multi_mul(&[(*point, k)])
+    }
+}
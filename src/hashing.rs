@@ -0,0 +1,63 @@
+use crate::fpelem::{
+    count_bits_generic_uint, generic_uint_from_be_bytes, generic_uint_to_be_bytes, FpElem,
+    GenericUInt,
+};
+use sha2::{Digest, Sha224, Sha256, Sha384, Sha512};
+
+// Message digest used by KeyChain::sign_message and friends, and as the HMAC for RFC 6979 nonces
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum HashAlgorithm {
+    Sha224,
+    #[default]
+    Sha256,
+    Sha384,
+    Sha512,
+}
+
+impl HashAlgorithm {
+    pub fn digest(&self, message: &[u8]) -> Vec<u8> {
+        match self {
+            HashAlgorithm::Sha224 => Sha224::digest(message).to_vec(),
+            HashAlgorithm::Sha256 => Sha256::digest(message).to_vec(),
+            HashAlgorithm::Sha384 => Sha384::digest(message).to_vec(),
+            HashAlgorithm::Sha512 => Sha512::digest(message).to_vec(),
+        }
+    }
+
+    pub fn output_size(&self) -> usize {
+        match self {
+            HashAlgorithm::Sha224 => 28,
+            HashAlgorithm::Sha256 => 32,
+            HashAlgorithm::Sha384 => 48,
+            HashAlgorithm::Sha512 => 64,
+        }
+    }
+}
+
+// bits2int (FIPS 186-5 / RFC 6979 2.3.2) - the leftmost qlen bits of the input as an integer
+// only the first ceil(qlen/8) bytes are ever read so this can't overflow a T that holds q
+pub fn bits2int<T: GenericUInt>(bytes: &[u8], qlen: u64) -> T {
+    let qbytes = qlen.div_ceil(8) as usize;
+    let bytes = &bytes[..bytes.len().min(qbytes)];
+    let out: T = generic_uint_from_be_bytes(bytes);
+    let blen = 8 * bytes.len() as u64;
+    if blen > qlen {
+        // excess is always < 8 here
+        out >> T::from((blen - qlen) as u8)
+    } else {
+        out
+    }
+}
+
+// bits2octets (RFC 6979 2.3.4) - bits2int reduced mod q, written back out as ceil(qlen/8) bytes
+pub fn bits2octets<T: GenericUInt>(bytes: &[u8], order: T) -> Vec<u8> {
+    let qlen = count_bits_generic_uint(order);
+    let z: T = bits2int(bytes, qlen);
+    generic_uint_to_be_bytes(z % order, qlen.div_ceil(8) as usize)
+}
+
+// the z that ECDSA signs for a message digest, truncated to the bit length of n then reduced mod n
+pub fn digest_to_scalar<T: GenericUInt>(digest: &[u8], order: T) -> FpElem<T> {
+    let qlen = count_bits_generic_uint(order);
+    FpElem::new(bits2int(digest, qlen), order)
+}
@@ -0,0 +1,155 @@
+use crate::ellipticcurve::ECPoint;
+use crate::fpelem::FieldElement;
+use std::ops::{Add, Neg};
+
+// A point in Jacobian coordinates - (X : Y : Z) is the affine point (X/Z^2, Y/Z^3), and Z = 0 is
+// infinity. Adding and doubling these needs no inversions, so scalar multiplication runs on them
+// and only pays for the single inversion in to_affine at the end.
+#[derive(Debug, Clone, Copy)]
+pub struct JacobianPoint<T> {
+    x: T,
+    y: T,
+    z: T,
+    a: T,
+    b: T,
+}
+
+impl<F: FieldElement> JacobianPoint<F> {
+    // (x : y : 1), or (1 : 1 : 0) for infinity
+    pub fn from_affine(point: &ECPoint<F>) -> Self {
+        let (a, b) = point.curve();
+        let zero = a.with_number(F::Int::from(0));
+        let one = a.with_number(F::Int::from(1));
+        match point.position {
+            Some((x, y)) => JacobianPoint { x, y, z: one, a, b },
+            None => JacobianPoint {
+                x: one,
+                y: one,
+                z: zero,
+                a,
+                b,
+            },
+        }
+    }
+
+    pub fn to_affine(&self) -> ECPoint<F> {
+        if self.is_infinity() {
+            return ECPoint::from_parts(None, self.a, self.b);
+        }
+        let zinv = self.z.with_number(F::Int::from(1)) / self.z;
+        let zinv2 = zinv * zinv;
+        let (x, y) = (self.x * zinv2, self.y * zinv2 * zinv);
+        ECPoint::from_parts(Some((x, y)), self.a, self.b)
+    }
+
+    pub fn is_infinity(&self) -> bool {
+        self.z.is_zero()
+    }
+
+    // dbl-2007-bl from the Explicit-Formulas Database, with the a Z^4 term skipped when a = 0
+    // Y = 0 (a point of order 2) gives Z3 = 0, i.e. infinity, without any special casing
+    pub fn double(&self) -> Self {
+        if self.is_infinity() {
+            return *self;
+        }
+        let (x1, y1, z1) = (self.x, self.y, self.z);
+        let xx = x1 * x1;
+        let yy = y1 * y1;
+        let yyyy = yy * yy;
+        let zz = z1 * z1;
+        let x1yy = x1 + yy;
+        let s = x1yy * x1yy - xx - yyyy;
+        let s = s + s;
+        let mut m = xx + xx + xx;
+        if !self.a.is_zero() {
+            m = m + self.a * zz * zz;
+        }
+        let t = m * m - (s + s);
+        let yyyy2 = yyyy + yyyy;
+        let yyyy4 = yyyy2 + yyyy2;
+        let y3 = m * (s - t) - (yyyy4 + yyyy4);
+        let y1z1 = y1 + z1;
+        let z3 = y1z1 * y1z1 - yy - zz;
+        JacobianPoint {
+            x: t,
+            y: y3,
+            z: z3,
+            a: self.a,
+            b: self.b,
+        }
+    }
+}
+
+// add-2007-bl, falling back to double when the points turn out to be the same
+impl<F: FieldElement> Add for JacobianPoint<F> {
+    type Output = JacobianPoint<F>;
+    fn add(self, toadd: Self) -> JacobianPoint<F> {
+        assert!(
+            self.a == toadd.a && self.b == toadd.b,
+            "Curves must be the same"
+        );
+        if self.is_infinity() {
+            return toadd;
+        }
+        if toadd.is_infinity() {
+            return self;
+        }
+        let (x1, y1, z1) = (self.x, self.y, self.z);
+        let (x2, y2, z2) = (toadd.x, toadd.y, toadd.z);
+        let z1z1 = z1 * z1;
+        let z2z2 = z2 * z2;
+        let u1 = x1 * z2z2;
+        let u2 = x2 * z1z1;
+        let s1 = y1 * z2 * z2z2;
+        let s2 = y2 * z1 * z1z1;
+        let h = u2 - u1;
+        let r = s2 - s1;
+        let r = r + r;
+        if h.is_zero() {
+            // same x, so either the same point or each other's negatives
+            if r.is_zero() {
+                return self.double();
+            }
+            return JacobianPoint::from_affine(&ECPoint::from_parts(None, self.a, self.b));
+        }
+        let h2 = h + h;
+        let i = h2 * h2;
+        let j = h * i;
+        let v = u1 * i;
+        let x3 = r * r - j - (v + v);
+        let s1j = s1 * j;
+        let y3 = r * (v - x3) - (s1j + s1j);
+        let z1z2 = z1 + z2;
+        let z3 = (z1z2 * z1z2 - z1z1 - z2z2) * h;
+        JacobianPoint {
+            x: x3,
+            y: y3,
+            z: z3,
+            a: self.a,
+            b: self.b,
+        }
+    }
+}
+
+// -(X : Y : Z) = (X : -Y : Z)
+impl<F: FieldElement> Neg for JacobianPoint<F> {
+    type Output = JacobianPoint<F>;
+    fn neg(self) -> JacobianPoint<F> {
+        JacobianPoint { y: -self.y, ..self }
+    }
+}
+
+// same affine point - X1 Z2^2 = X2 Z1^2 and Y1 Z2^3 = Y2 Z1^3 - rather than same coordinates
+impl<F: FieldElement> PartialEq for JacobianPoint<F> {
+    fn eq(&self, other: &Self) -> bool {
+        if self.a != other.a || self.b != other.b {
+            return false;
+        }
+        if self.is_infinity() || other.is_infinity() {
+            return self.is_infinity() && other.is_infinity();
+        }
+        let z1z1 = self.z * self.z;
+        let z2z2 = other.z * other.z;
+        self.x * z2z2 == other.x * z1z1 && self.y * z2z2 * other.z == other.y * z1z1 * self.z
+    }
+}
@@ -1,6 +1,27 @@
 mod crypto;
+mod curves;
 mod ellipticcurve;
+mod encoding;
+mod fixedbase;
 mod fpelem;
+mod glv;
+mod hashing;
+mod jacobian;
+mod montgomery;
+mod msm;
+mod projective;
+mod rfc6979;
+mod validation;
 pub use crypto::*;
+pub use curves::*;
 pub use ellipticcurve::*;
+pub use encoding::*;
+pub use fixedbase::*;
 pub use fpelem::*;
+pub use glv::*;
+pub use hashing::*;
+pub use jacobian::*;
+pub use montgomery::*;
+pub use projective::*;
+pub use rfc6979::*;
+pub use validation::*;
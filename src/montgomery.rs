@@ -0,0 +1,511 @@
+use crate::ellipticcurve::ECPoint;
+use crate::fpelem::{
+    count_bits_generic_uint, forward_binop, mul_inv, select, type_bits, ArithmeticError,
+    FieldElement, FpElem, GenericUInt, Pow,
+};
+use std::fmt;
+use std::fmt::{Debug, Display, Formatter};
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign};
+
+// words in the biggest modulus handled, 16 words of 128 bits is 2048 bits
+const MAX_WORDS: usize = 16;
+
+// The constants for Montgomery arithmetic mod an odd p. Numbers are cut into words of w bits - half
+// the width of T so a product of two words fits, and at most 128 so shifts stay under 255 - and
+// with s words enough to hold p, R = 2^(ws) and n' = -1/p mod 2^w.
+#[derive(Debug, PartialEq, Clone)]
+pub struct MontgomeryParams<T> {
+    pub prime: T,
+    // R mod p (1 in Montgomery form) and R^2 mod p (for getting into it)
+    r: T,
+    r2: T,
+    n_prime: T,
+    // the words of p, split up front as every product needs them
+    n: [T; MAX_WORDS],
+    word_bits: u8,
+    words: usize,
+}
+
+impl<T: GenericUInt> MontgomeryParams<T> {
+    pub fn new(prime: T) -> Self {
+        let one = T::from(1);
+        assert!(
+            prime > one && prime & one == one,
+            "Montgomery form needs an odd modulus"
+        );
+        let word_bits = (type_bits::<T>() / 2).min(128) as u8;
+        let words = count_bits_generic_uint(prime).div_ceil(word_bits as u64) as usize;
+        assert!(
+            words <= MAX_WORDS,
+            "modulus is too wide for Montgomery form"
+        );
+        let base = one << T::from(word_bits);
+        let mask = base - one;
+        // Newton's iteration for 1/p mod 2^w, each step doubles the number of right bits and 1 is
+        // right mod 2 to start with as p is odd
+        let p0 = prime & mask;
+        let mut inverse = one;
+        for _ in 0..7 {
+            let t = (p0 * inverse) & mask;
+            inverse = (inverse * ((base + T::from(2) - t) & mask)) & mask;
+        }
+        // R mod p by doubling up from 1, which is slow but only happens once
+        let mut r = FpElem::new(one, prime);
+        for _ in 0..word_bits as usize * words {
+            r = r + r;
+        }
+        let mut params = MontgomeryParams {
+            prime,
+            r: r.number,
+            r2: (r * r).number,
+            n_prime: base - inverse,
+            n: [T::from(0); MAX_WORDS],
+            word_bits,
+            words,
+        };
+        params.n = params.split(prime);
+        params
+    }
+
+    // little endian words of x
+    fn split(&self, x: T) -> [T; MAX_WORDS] {
+        let shift = T::from(self.word_bits);
+        let mask = (T::from(1) << shift) - T::from(1);
+        let mut out = [T::from(0); MAX_WORDS];
+        let mut rest = x;
+        for word in out.iter_mut().take(self.words) {
+            *word = rest & mask;
+            rest = rest >> shift;
+        }
+        out
+    }
+
+    fn join(&self, words: &[T]) -> T {
+        let shift = T::from(self.word_bits);
+        words[..self.words]
+            .iter()
+            .rev()
+            .fold(T::from(0), |out, word| (out << shift) + *word)
+    }
+
+    // a b / R mod p for a, b < p, by coarsely integrated operand scanning (Koc, Acar and Kaliski,
+    // "Analyzing and comparing Montgomery multiplication algorithms") - for each word of b, add in
+    // a times it, then add the multiple of p that clears the bottom word and shift that word off.
+    // Every sum is at most (2^w - 1) + (2^w - 1)^2 + (2^w - 1) = 2^2w - 1, so fits in T
+    fn mul(&self, a: T, b: T) -> T {
+        let zero = T::from(0);
+        let one = T::from(1);
+        let shift = T::from(self.word_bits);
+        let mask = (one << shift) - one;
+        let s = self.words;
+        let (a, b, n) = (self.split(a), self.split(b), &self.n);
+        let mut t = [zero; MAX_WORDS + 2];
+        for bi in b.iter().take(s) {
+            let mut carry = zero;
+            for j in 0..s {
+                let sum = t[j] + a[j] * *bi + carry;
+                t[j] = sum & mask;
+                carry = sum >> shift;
+            }
+            let sum = t[s] + carry;
+            t[s] = sum & mask;
+            t[s + 1] = sum >> shift;
+            let m = (t[0] * self.n_prime) & mask;
+            let mut carry = (t[0] + m * n[0]) >> shift;
+            for j in 1..s {
+                let sum = t[j] + m * n[j] + carry;
+                t[j - 1] = sum & mask;
+                carry = sum >> shift;
+            }
+            let sum = t[s] + carry;
+            t[s - 1] = sum & mask;
+            t[s] = t[s + 1] + (sum >> shift);
+        }
+        // t < 2p here, with t[s] the bit above the top word, and it's t - p when that's at least
+        // 0. t - p might not fit in T so it's taken word by word, and which one to keep is picked
+        // by arithmetic rather than a branch
+        let (diff, no_borrow) = self.sub_words(&t, n);
+        let over = t[s] + no_borrow - t[s] * no_borrow;
+        select(self.join(&t), self.join(&diff), over)
+    }
+
+    // a + b mod p for a, b < p, without branching on the values - a + b might not fit in T either
+    fn add(&self, a: T, b: T) -> T {
+        let (sum, carry) = self.add_words(&self.split(a), &self.split(b));
+        let (diff, no_borrow) = self.sub_words(&sum, &self.n);
+        let over = carry + no_borrow - carry * no_borrow;
+        select(self.join(&sum), self.join(&diff), over)
+    }
+
+    // a - b mod p for a, b < p, adding p back when it went below 0
+    fn sub(&self, a: T, b: T) -> T {
+        let (diff, no_borrow) = self.sub_words(&self.split(a), &self.split(b));
+        let (wrapped, _) = self.add_words(&diff, &self.n);
+        select(self.join(&wrapped), self.join(&diff), no_borrow)
+    }
+
+    // x + y on the bottom s words, and the carry out of the top one
+    fn add_words(&self, x: &[T], y: &[T]) -> ([T; MAX_WORDS], T) {
+        let one = T::from(1);
+        let shift = T::from(self.word_bits);
+        let mask = (one << shift) - one;
+        let mut out = [T::from(0); MAX_WORDS];
+        let mut carry = T::from(0);
+        for j in 0..self.words {
+            let sum = x[j] + y[j] + carry;
+            out[j] = sum & mask;
+            carry = sum >> shift;
+        }
+        (out, carry)
+    }
+
+    // x - y mod 2^(ws) on the bottom s words, and 1 when nothing was borrowed off the top, i.e.
+    // x >= y. Each word gets 2^w added before taking away so it never goes below 0
+    fn sub_words(&self, x: &[T], y: &[T]) -> ([T; MAX_WORDS], T) {
+        let one = T::from(1);
+        let shift = T::from(self.word_bits);
+        let mask = (one << shift) - one;
+        let mut out = [T::from(0); MAX_WORDS];
+        let mut borrow = T::from(0);
+        for j in 0..self.words {
+            let d = x[j] + mask + one - y[j] - borrow;
+            out[j] = d & mask;
+            borrow = one - (d >> shift);
+        }
+        (out, one - borrow)
+    }
+
+    fn encode(&self, number: T) -> T {
+        self.encode_reduced(number % self.prime)
+    }
+
+    // encode for a number already below p, which skips the division - that isn't constant time
+    fn encode_reduced(&self, number: T) -> T {
+        self.mul(number, self.r2)
+    }
+
+    fn decode(&self, mont: T) -> T {
+        self.mul(mont, T::from(1))
+    }
+}
+
+// An element of F_p kept as aR mod p rather than a, so multiplying is a Montgomery product with no
+// division by p. Getting in and out costs a product each, and working out the MontgomeryParams
+// costs a lot more, so make them once for p and hand every element mod p a reference to them.
+// Like FpElem, p isn't checked for primality and Div is only right when it's prime.
+#[derive(Clone, Copy)]
+pub struct MontFpElem<'p, T> {
+    mont: T,
+    params: &'p MontgomeryParams<T>,
+}
+
+impl<'p, T: GenericUInt> MontFpElem<'p, T> {
+    pub fn with_params(number: T, params: &'p MontgomeryParams<T>) -> Self {
+        MontFpElem {
+            mont: params.encode(number),
+            params,
+        }
+    }
+
+    // with_params for a number already below p, and without branching on it
+    pub(crate) fn from_reduced(number: T, params: &'p MontgomeryParams<T>) -> Self {
+        MontFpElem {
+            mont: params.encode_reduced(number),
+            params,
+        }
+    }
+
+    // aR itself, and the element it's the Montgomery form of - for storing elements without the
+    // params, as FixedBaseTable does
+    pub(crate) fn mont(&self) -> T {
+        self.mont
+    }
+
+    pub(crate) fn from_mont(mont: T, params: &'p MontgomeryParams<T>) -> Self {
+        MontFpElem { mont, params }
+    }
+
+    // the canonical value in [0, p)
+    pub fn number(&self) -> T {
+        self.params.decode(self.mont)
+    }
+
+    pub fn prime(&self) -> T {
+        self.params.prime
+    }
+
+    pub fn params(&self) -> &'p MontgomeryParams<T> {
+        self.params
+    }
+
+    pub fn to_fp(&self) -> FpElem<T> {
+        FpElem::new(self.number(), self.params.prime)
+    }
+
+    // aR -> (aR)^-1 = a^-1 R^-1 by extended Euclid, then two products with R^2 take that to
+    // a^-1 R. Garbage when there's no inverse, as FpElem's Div
+    fn invert(&self) -> Self {
+        let params = self.params;
+        let inverse = mul_inv(self.mont, params.prime);
+        MontFpElem {
+            mont: params.mul(params.mul(inverse, params.r2), params.r2),
+            params,
+        }
+    }
+
+    // as FpElem::try_inverse and friends
+    pub fn try_inverse(&self) -> Result<Self, ArithmeticError> {
+        let inverse = self.invert();
+        if (inverse * self).mont != self.params.r {
+            return Err(ArithmeticError::NotInvertible);
+        }
+        Ok(inverse)
+    }
+
+    // as FpElem::try_sum
+    pub fn try_sum<I: IntoIterator<Item = Self>>(elems: I) -> Option<Self> {
+        elems.into_iter().reduce(|sum, x| sum + x)
+    }
+
+    pub fn checked_add(&self, other: &Self) -> Result<Self, ArithmeticError> {
+        self.same_prime(other)?;
+        Ok(self + other)
+    }
+
+    pub fn checked_sub(&self, other: &Self) -> Result<Self, ArithmeticError> {
+        self.same_prime(other)?;
+        Ok(self - other)
+    }
+
+    pub fn checked_mul(&self, other: &Self) -> Result<Self, ArithmeticError> {
+        self.same_prime(other)?;
+        Ok(self * other)
+    }
+
+    pub fn checked_div(&self, other: &Self) -> Result<Self, ArithmeticError> {
+        self.same_prime(other)?;
+        Ok(self * other.try_inverse()?)
+    }
+
+    fn same_prime(&self, other: &Self) -> Result<(), ArithmeticError> {
+        if self.params.prime != other.params.prime {
+            return Err(ArithmeticError::ModulusMismatch);
+        }
+        Ok(())
+    }
+}
+
+// the adds and subtracts only ever see reduced values, so unlike add_mod and sub_mod there's no
+// reducing first, and like the products they don't branch on the values
+impl<'p, T: GenericUInt> Add for &MontFpElem<'p, T> {
+    type Output = MontFpElem<'p, T>;
+    fn add(self, toadd: &MontFpElem<'p, T>) -> MontFpElem<'p, T> {
+        assert!(
+            self.params.prime == toadd.params.prime,
+            "Prime base must be the same"
+        );
+        MontFpElem {
+            mont: self.params.add(self.mont, toadd.mont),
+            params: self.params,
+        }
+    }
+}
+
+impl<'p, T: GenericUInt> Sub for &MontFpElem<'p, T> {
+    type Output = MontFpElem<'p, T>;
+    fn sub(self, tosub: &MontFpElem<'p, T>) -> MontFpElem<'p, T> {
+        assert!(
+            self.params.prime == tosub.params.prime,
+            "Prime base must be the same"
+        );
+        MontFpElem {
+            mont: self.params.sub(self.mont, tosub.mont),
+            params: self.params,
+        }
+    }
+}
+
+impl<'p, T: GenericUInt> Mul for &MontFpElem<'p, T> {
+    type Output = MontFpElem<'p, T>;
+    fn mul(self, tomul: &MontFpElem<'p, T>) -> MontFpElem<'p, T> {
+        assert!(
+            self.params.prime == tomul.params.prime,
+            "Prime base must be the same"
+        );
+        MontFpElem {
+            mont: self.params.mul(self.mont, tomul.mont),
+            params: self.params,
+        }
+    }
+}
+
+#[allow(clippy::suspicious_arithmetic_impl)]
+impl<'p, T: GenericUInt> Div for &MontFpElem<'p, T> {
+    type Output = MontFpElem<'p, T>;
+    fn div(self, rhs: &MontFpElem<'p, T>) -> MontFpElem<'p, T> {
+        Mul::mul(self, &rhs.invert())
+    }
+}
+
+impl<'p, T: GenericUInt> Mul<T> for &MontFpElem<'p, T> {
+    type Output = MontFpElem<'p, T>;
+    fn mul(self, tomul: T) -> MontFpElem<'p, T> {
+        Mul::mul(self, &MontFpElem::with_params(tomul, self.params))
+    }
+}
+
+forward_binop!(['p, T: GenericUInt] MontFpElem<'p, T>, Add, add, AddAssign, add_assign);
+forward_binop!(['p, T: GenericUInt] MontFpElem<'p, T>, Sub, sub, SubAssign, sub_assign);
+forward_binop!(['p, T: GenericUInt] MontFpElem<'p, T>, Mul, mul, MulAssign, mul_assign);
+forward_binop!(['p, T: GenericUInt] MontFpElem<'p, T>, Div, div, DivAssign, div_assign);
+
+impl<'p, T: GenericUInt> Mul<T> for MontFpElem<'p, T> {
+    type Output = MontFpElem<'p, T>;
+    fn mul(self, tomul: T) -> MontFpElem<'p, T> {
+        Mul::mul(&self, tomul)
+    }
+}
+
+impl<T: GenericUInt> MulAssign<T> for MontFpElem<'_, T> {
+    fn mul_assign(&mut self, tomul: T) {
+        *self = Mul::mul(&*self, tomul);
+    }
+}
+
+// 0 is 0 in Montgomery form too, so -a is still p - a with -0 = 0
+impl<'p, T: GenericUInt> Neg for &MontFpElem<'p, T> {
+    type Output = MontFpElem<'p, T>;
+    fn neg(self) -> MontFpElem<'p, T> {
+        let zero = MontFpElem {
+            mont: T::from(0),
+            params: self.params,
+        };
+        Sub::sub(&zero, self)
+    }
+}
+
+impl<'p, T: GenericUInt> Neg for MontFpElem<'p, T> {
+    type Output = MontFpElem<'p, T>;
+    fn neg(self) -> MontFpElem<'p, T> {
+        -&self
+    }
+}
+
+// as FpElem's
+impl<'p, T: GenericUInt> Sum<MontFpElem<'p, T>> for Option<MontFpElem<'p, T>> {
+    fn sum<I: Iterator<Item = MontFpElem<'p, T>>>(iter: I) -> Self {
+        MontFpElem::try_sum(iter)
+    }
+}
+
+impl<'a, 'p, T: GenericUInt> Sum<&'a MontFpElem<'p, T>> for Option<MontFpElem<'p, T>> {
+    fn sum<I: Iterator<Item = &'a MontFpElem<'p, T>>>(iter: I) -> Self {
+        MontFpElem::try_sum(iter.copied())
+    }
+}
+
+// square and multiply as pow_mod, starting from R which is 1 here. The products don't branch on
+// the base, but which ones are done follows the exponent's bits, so the exponent shouldn't be a
+// secret - a^(p-2) for a secret a is fine as p - 2 isn't
+impl<T: GenericUInt> Pow<T> for MontFpElem<'_, T> {
+    fn pow(&self, exponent: T) -> Self {
+        let zero = T::from(0);
+        let one = T::from(1);
+        let params = self.params;
+        let mut result = params.r;
+        let mut current = self.mont;
+        let mut e = exponent;
+        while e != zero {
+            if e & one != zero {
+                result = params.mul(result, current);
+            }
+            current = params.mul(current, current);
+            e = e >> one;
+        }
+        MontFpElem {
+            mont: result,
+            params,
+        }
+    }
+}
+
+impl<T: GenericUInt> FieldElement for MontFpElem<'_, T> {
+    type Int = T;
+    fn with_number(&self, number: T) -> Self {
+        MontFpElem::with_params(number, self.params)
+    }
+    fn to_int(&self) -> T {
+        self.number()
+    }
+    fn modulus(&self) -> T {
+        self.params.prime
+    }
+    fn try_inverse(&self) -> Result<Self, ArithmeticError> {
+        MontFpElem::try_inverse(self)
+    }
+    fn select(&self, other: &Self, choice: T) -> Self {
+        MontFpElem {
+            mont: select(self.mont, other.mont, choice),
+            params: self.params,
+        }
+    }
+    fn is_zero(&self) -> bool {
+        self.mont == T::from(0)
+    }
+}
+
+// the same element whoever's copy of the params it points at
+impl<T: GenericUInt> PartialEq for MontFpElem<'_, T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.mont == other.mont && self.params.prime == other.params.prime
+    }
+}
+
+// the Montgomery form would only confuse, show the canonical value
+impl<T: GenericUInt + Debug> Debug for MontFpElem<'_, T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MontFpElem")
+            .field("number", &self.number())
+            .field("prime", &self.params.prime)
+            .finish()
+    }
+}
+
+impl<T: GenericUInt + Display> Display for MontFpElem<'_, T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.number())
+    }
+}
+
+impl<T: GenericUInt> From<MontFpElem<'_, T>> for FpElem<T> {
+    fn from(x: MontFpElem<'_, T>) -> Self {
+        x.to_fp()
+    }
+}
+
+impl<T: GenericUInt> ECPoint<FpElem<T>> {
+    // the same point with its coordinates and curve moved into Montgomery form mod params' prime,
+    // which has to be the point's p
+    pub fn to_montgomery<'p>(&self, params: &'p MontgomeryParams<T>) -> ECPoint<MontFpElem<'p, T>> {
+        let (a, b) = self.curve();
+        assert!(a.prime == params.prime, "Prime base must be the same");
+        let into = |x: FpElem<T>| MontFpElem::with_params(x.number, params);
+        ECPoint::from_parts(
+            self.position.map(|(x, y)| (into(x), into(y))),
+            into(a),
+            into(b),
+        )
+    }
+}
+
+impl<T: GenericUInt> From<ECPoint<MontFpElem<'_, T>>> for ECPoint<FpElem<T>> {
+    fn from(point: ECPoint<MontFpElem<'_, T>>) -> Self {
+        let (a, b) = point.curve();
+        ECPoint::from_parts(
+            point.position.map(|(x, y)| (x.to_fp(), y.to_fp())),
+            a.to_fp(),
+            b.to_fp(),
+        )
+    }
+}
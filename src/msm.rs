@@ -0,0 +1,227 @@
+use crate::ellipticcurve::ECPoint;
+use crate::fpelem::{count_bits_generic_uint, FieldElement, GenericUInt};
+use crate::jacobian::JacobianPoint;
+
+// largest Pippenger window tried, 2^16 buckets is already far more than any batch here needs
+const MAX_PIPPENGER_WINDOW: u64 = 16;
+
+impl<T: GenericUInt, F: FieldElement<Int = T>> ECPoint<F> {
+    // Sum of k_i P_i sharing one run of doublings, for public scalars - none of this is constant
+    // time. Two terms use Shamir's trick, more use interleaved wNAF (Straus) or, once the batch is
+    // big enough for it to need fewer additions, Pippenger's bucket method.
+    // terms must be non empty and all on the same curve
+    pub fn multi_mul(terms: &[(Self, T)]) -> Self {
+        assert!(!terms.is_empty(), "multi_mul needs at least one term");
+        assert!(
+            terms.iter().all(|(point, _)| point.same_curve(&terms[0].0)),
+            "Curves must be the same"
+        );
+        let bits = terms
+            .iter()
+            .map(|(_, k)| count_bits_generic_uint(*k))
+            .max()
+            .unwrap_or(1);
+        match terms.len() {
+            1 => Self::straus(terms),
+            2 => Self::shamir(&terms[0], &terms[1]),
+            n => {
+                let window = pippenger_window(n, bits);
+                if pippenger_cost(n, bits, window) < straus_cost(n, bits) {
+                    Self::pippenger(terms, window)
+                } else {
+                    Self::straus(terms)
+                }
+            }
+        }
+    }
+
+    fn jacobian_infinity(&self) -> JacobianPoint<F> {
+        JacobianPoint::from_affine(&self.infinity())
+    }
+
+    // Shamir's trick two bits at a time - a table of iP + jQ for i, j < 4, then two doublings
+    // and at most one addition for each pair of bits
+    fn shamir(lhs: &(Self, T), rhs: &(Self, T)) -> Self {
+        let (p, q) = (
+            JacobianPoint::from_affine(&lhs.0),
+            JacobianPoint::from_affine(&rhs.0),
+        );
+        let infinity = lhs.0.jacobian_infinity();
+        // table[4i + j] = iP + jQ
+        let mut table = [infinity; 16];
+        for i in 0..4 {
+            if i > 0 {
+                table[4 * i] = table[4 * (i - 1)] + p;
+            }
+            for j in 1..4 {
+                table[4 * i + j] = table[4 * i + j - 1] + q;
+            }
+        }
+        let (pbits, qbits) = (to_bits(lhs.1), to_bits(rhs.1));
+        let top = pbits.len().max(qbits.len());
+        let bit = |bits: &Vec<bool>, i: usize| bits.get(i) == Some(&true);
+        let mut result = infinity;
+        for pair in (0..top.div_ceil(2)).rev() {
+            result = result.double().double();
+            let (lo, hi) = (2 * pair, 2 * pair + 1);
+            let i = 2 * bit(&pbits, hi) as usize + bit(&pbits, lo) as usize;
+            let j = 2 * bit(&qbits, hi) as usize + bit(&qbits, lo) as usize;
+            if i + j != 0 {
+                result = result + table[4 * i + j];
+            }
+        }
+        result.to_affine()
+    }
+
+    // interleaved width w NAF, each term with its own window and odd multiples
+    pub(crate) fn straus(terms: &[(Self, T)]) -> Self {
+        let mut expansions = Vec::with_capacity(terms.len());
+        let mut tables = Vec::with_capacity(terms.len());
+        for (point, k) in terms.iter() {
+            let w = window_for(count_bits_generic_uint(*k));
+            expansions.push(wnaf(*k, w));
+            tables.push(odd_multiples(JacobianPoint::from_affine(point), w));
+        }
+        let top = expansions
+            .iter()
+            .map(|digits| digits.len())
+            .max()
+            .unwrap_or(0);
+        let mut result = terms[0].0.jacobian_infinity();
+        for i in (0..top).rev() {
+            result = result.double();
+            for (digits, odd) in expansions.iter().zip(tables.iter()) {
+                match digits.get(i) {
+                    Some(d) if *d > 0 => result = result + odd[(*d / 2) as usize],
+                    Some(d) if *d < 0 => result = result + -odd[(-*d / 2) as usize],
+                    _ => {}
+                }
+            }
+        }
+        result.to_affine()
+    }
+
+    // Pippenger - for each window of c bits, add every point into the bucket for its digit, then
+    // sum j * bucket_j with a running sum from the top bucket down
+    fn pippenger(terms: &[(Self, T)], c: u64) -> Self {
+        let c = c as usize;
+        let expansions: Vec<Vec<bool>> = terms.iter().map(|(_, k)| to_bits(*k)).collect();
+        let points: Vec<JacobianPoint<F>> = terms
+            .iter()
+            .map(|(point, _)| JacobianPoint::from_affine(point))
+            .collect();
+        let top = expansions.iter().map(|bits| bits.len()).max().unwrap_or(0);
+        let infinity = terms[0].0.jacobian_infinity();
+        let mut result = infinity;
+        for window in (0..top.div_ceil(c)).rev() {
+            for _ in 0..c {
+                result = result.double();
+            }
+            let mut buckets = vec![infinity; (1 << c) - 1];
+            for (point, bits) in points.iter().zip(expansions.iter()) {
+                let mut digit = 0usize;
+                for j in 0..c {
+                    if bits.get(window * c + j) == Some(&true) {
+                        digit |= 1 << j;
+                    }
+                }
+                if digit != 0 {
+                    buckets[digit - 1] = buckets[digit - 1] + *point;
+                }
+            }
+            let mut running = infinity;
+            let mut sum = infinity;
+            for bucket in buckets.iter().rev() {
+                running = running + *bucket;
+                sum = sum + running;
+            }
+            result = result + sum;
+        }
+        result.to_affine()
+    }
+}
+
+// rough number of additions for n terms of this many bits, the doublings are the same either way
+fn straus_cost(n: usize, bits: u64) -> u64 {
+    let w = window_for(bits) as u64;
+    n as u64 * (bits / (w + 1) + (1 << (w - 2)))
+}
+
+fn pippenger_cost(n: usize, bits: u64, c: u64) -> u64 {
+    bits.div_ceil(c) * (n as u64 + (2 << c))
+}
+
+fn pippenger_window(n: usize, bits: u64) -> u64 {
+    (1..=MAX_PIPPENGER_WINDOW)
+        .min_by_key(|c| pippenger_cost(n, bits, *c))
+        .unwrap_or(1)
+}
+
+// P, 3P, 5P, ..., (2^(w-1) - 1)P
+fn odd_multiples<F: FieldElement>(base: JacobianPoint<F>, w: u8) -> Vec<JacobianPoint<F>> {
+    let twice = base.double();
+    let mut odd = vec![base];
+    for i in 1..1usize << (w - 2) {
+        odd.push(odd[i - 1] + twice);
+    }
+    odd
+}
+
+// window width for a scalar of this many bits, trading the 2^(w-2) precomputed points against
+// one add per w + 1 bits
+fn window_for(bits: u64) -> u8 {
+    match bits {
+        0..=16 => 2,
+        17..=48 => 3,
+        49..=160 => 4,
+        161..=384 => 5,
+        _ => 6,
+    }
+}
+
+// width w NAF of k, least significant digit first - every nonzero digit is odd and below 2^(w-1)
+// in size, and there's at most one nonzero digit in any w in a row. w must be between 2 and 7
+fn wnaf<T: GenericUInt>(k: T, w: u8) -> Vec<i8> {
+    let zero = T::from(0);
+    let one = T::from(1);
+    let half = 1u8 << (w - 1);
+    let mut k = k;
+    let mut digits = Vec::new();
+    while k != zero {
+        if k & one == zero {
+            digits.push(0);
+            k = k >> one;
+            continue;
+        }
+        // m = k mod 2^w
+        let mut m = 0u8;
+        for j in 0..w {
+            if (k >> T::from(j)) & one != zero {
+                m |= 1 << j;
+            }
+        }
+        // k - m is a multiple of 2^w, and for a negative digit (k - (m - 2^w)) / 2 is written so
+        // it can't overflow
+        if m < half {
+            digits.push(m as i8);
+            k = (k - T::from(m)) >> one;
+        } else {
+            digits.push((m as i16 - 2 * half as i16) as i8);
+            k = ((k - T::from(m)) >> one) + T::from(half);
+        }
+    }
+    digits
+}
+
+// binary expansion, least significant bit first
+fn to_bits<T: GenericUInt>(k: T) -> Vec<bool> {
+    let zero = T::from(0);
+    let one = T::from(1);
+    let mut k = k;
+    let mut bits = Vec::new();
+    while k != zero {
+        bits.push(k & one != zero);
+        k = k >> one;
+    }
+    bits
+}
@@ -0,0 +1,216 @@
+use crate::ellipticcurve::ECPoint;
+use crate::fpelem::{FieldElement, Pow};
+use std::ops::Add;
+
+// A point in homogeneous projective coordinates - (X : Y : Z) is the affine point (X/Z, Y/Z), and
+// infinity is (0 : 1 : 0). Addition here uses the complete formulas of Renes, Costello and Batina
+// (https://eprint.iacr.org/2015/1060), which have no branches and no exceptional cases - P + P,
+// P + (-P) and infinity on either side all go down the same path. That holds on curves of odd
+// order, which is every named curve in CurveDomain; when the curve has points of order 2 and
+// P - Q is one of them the formulas give (0 : 0 : 0). ECPoint::mul_secret and FixedBaseTable check
+// for that at the end and redo the multiplication with ECPoint's Mul, in Jacobian coordinates.
+// Those two run it on MontFpElem, whose arithmetic doesn't branch either.
+#[derive(Debug, Clone, Copy)]
+pub struct ProjectivePoint<T> {
+    x: T,
+    y: T,
+    z: T,
+    a: T,
+    b: T,
+}
+
+impl<F: FieldElement> ProjectivePoint<F> {
+    // (x : y : 1), or (0 : 1 : 0) for infinity
+    pub fn from_affine(point: &ECPoint<F>) -> Self {
+        let (a, b) = point.curve();
+        let zero = a.with_number(F::Int::from(0));
+        let one = a.with_number(F::Int::from(1));
+        match point.position {
+            Some((x, y)) => ProjectivePoint { x, y, z: one, a, b },
+            None => ProjectivePoint {
+                x: zero,
+                y: one,
+                z: zero,
+                a,
+                b,
+            },
+        }
+    }
+
+    pub fn is_infinity(&self) -> bool {
+        self.z.is_zero()
+    }
+
+    // (0 : 0 : 0), what the formulas give when P - Q has order 2. It isn't a point, and anything
+    // added to or doubled from it stays (0 : 0 : 0)
+    pub(crate) fn is_degenerate(&self) -> bool {
+        self.x.is_zero() && self.y.is_zero() && self.z.is_zero()
+    }
+
+    // the complete formulas double just fine, there's no separate routine
+    pub fn double(&self) -> Self {
+        *self + *self
+    }
+
+    // RCB algorithm 7, for a = 0 (secp256k1 and the other Koblitz curves)
+    fn add_a_zero(&self, other: &Self) -> Self {
+        let (x1, y1, z1) = (self.x, self.y, self.z);
+        let (x2, y2, z2) = (other.x, other.y, other.z);
+        let b3 = self.b + self.b + self.b;
+        let t0 = x1 * x2;
+        let t1 = y1 * y2;
+        let t2 = z1 * z2;
+        let t3 = (x1 + y1) * (x2 + y2) - (t0 + t1);
+        let t4 = (y1 + z1) * (y2 + z2) - (t1 + t2);
+        let x3 = (x1 + z1) * (x2 + z2);
+        let y3 = x3 - (t0 + t2);
+        let t0 = t0 + t0 + t0;
+        let t2 = b3 * t2;
+        let z3 = t1 + t2;
+        let t1 = t1 - t2;
+        let y3 = b3 * y3;
+        let x3 = t3 * t1 - t4 * y3;
+        let y3 = t1 * z3 + y3 * t0;
+        let z3 = z3 * t4 + t0 * t3;
+        ProjectivePoint {
+            x: x3,
+            y: y3,
+            z: z3,
+            a: self.a,
+            b: self.b,
+        }
+    }
+
+    // RCB algorithm 4, for a = -3 (the NIST P-curves)
+    fn add_a_minus_three(&self, other: &Self) -> Self {
+        let (x1, y1, z1) = (self.x, self.y, self.z);
+        let (x2, y2, z2) = (other.x, other.y, other.z);
+        let b = self.b;
+        let t0 = x1 * x2;
+        let t1 = y1 * y2;
+        let t2 = z1 * z2;
+        let t3 = (x1 + y1) * (x2 + y2) - (t0 + t1);
+        let t4 = (y1 + z1) * (y2 + z2) - (t1 + t2);
+        let x3 = (x1 + z1) * (x2 + z2);
+        let y3 = x3 - (t0 + t2);
+        let z3 = b * t2;
+        let x3 = y3 - z3;
+        let x3 = x3 + x3 + x3;
+        let z3 = t1 - x3;
+        let x3 = t1 + x3;
+        let y3 = b * y3;
+        let t2 = t2 + t2 + t2;
+        let y3 = y3 - t2 - t0;
+        let y3 = y3 + y3 + y3;
+        let t0 = t0 + t0 + t0 - t2;
+        let t1 = t4 * y3;
+        let t2 = t0 * y3;
+        let y3 = x3 * z3 + t2;
+        let x3 = t3 * x3 - t1;
+        let z3 = t4 * z3 + t3 * t0;
+        ProjectivePoint {
+            x: x3,
+            y: y3,
+            z: z3,
+            a: self.a,
+            b: self.b,
+        }
+    }
+
+    // RCB algorithm 1, for any a
+    fn add_any_a(&self, other: &Self) -> Self {
+        let (x1, y1, z1) = (self.x, self.y, self.z);
+        let (x2, y2, z2) = (other.x, other.y, other.z);
+        let (a, b3) = (self.a, self.b + self.b + self.b);
+        let t0 = x1 * x2;
+        let t1 = y1 * y2;
+        let t2 = z1 * z2;
+        let t3 = (x1 + y1) * (x2 + y2) - (t0 + t1);
+        let t4 = (x1 + z1) * (x2 + z2) - (t0 + t2);
+        let t5 = (y1 + z1) * (y2 + z2) - (t1 + t2);
+        let z3 = a * t4 + b3 * t2;
+        let x3 = t1 - z3;
+        let z3 = t1 + z3;
+        let y3 = x3 * z3;
+        let t1 = t0 + t0 + t0;
+        let t2 = a * t2;
+        let t4 = b3 * t4;
+        let t1 = t1 + t2;
+        let t2 = a * (t0 - t2);
+        let t4 = t4 + t2;
+        let y3 = y3 + t1 * t4;
+        let x3 = t3 * x3 - t5 * t4;
+        let z3 = t5 * z3 + t3 * t1;
+        ProjectivePoint {
+            x: x3,
+            y: y3,
+            z: z3,
+            a: self.a,
+            b: self.b,
+        }
+    }
+
+    // (X, Y, Z), and the point on the same curve with the given ones - for storing points without
+    // the curve, as FixedBaseTable does
+    pub(crate) fn coordinates(&self) -> [F; 3] {
+        [self.x, self.y, self.z]
+    }
+
+    pub(crate) fn with_coordinates(&self, [x, y, z]: [F; 3]) -> Self {
+        ProjectivePoint { x, y, z, ..*self }
+    }
+
+    // swap the two points when choice is 1 and leave them when it's 0, picking every coordinate
+    // with FieldElement::select so there's no branch on choice
+    pub(crate) fn swap_if(&mut self, other: &mut Self, choice: F::Int) {
+        let (x, y, z) = (self.x, self.y, self.z);
+        self.x = x.select(&other.x, choice);
+        self.y = y.select(&other.y, choice);
+        self.z = z.select(&other.z, choice);
+        other.x = other.x.select(&x, choice);
+        other.y = other.y.select(&y, choice);
+        other.z = other.z.select(&z, choice);
+    }
+}
+
+impl<F: FieldElement + Pow<F::Int>> ProjectivePoint<F> {
+    // 1/Z by Fermat, Z^(p-2), so the inversion doesn't branch on Z the way extended Euclid does
+    pub fn to_affine(&self) -> ECPoint<F> {
+        if self.is_infinity() {
+            return ECPoint::from_parts(None, self.a, self.b);
+        }
+        let zinv = self.z.pow(self.z.modulus() - F::Int::from(2));
+        ECPoint::from_parts(Some((self.x * zinv, self.y * zinv)), self.a, self.b)
+    }
+}
+
+// which formula is picked depends only on the curve, never on the points
+impl<F: FieldElement> Add for ProjectivePoint<F> {
+    type Output = ProjectivePoint<F>;
+    fn add(self, toadd: Self) -> ProjectivePoint<F> {
+        assert!(
+            self.a == toadd.a && self.b == toadd.b,
+            "Curves must be the same"
+        );
+        if self.a.is_zero() {
+            self.add_a_zero(&toadd)
+        } else if self.a == -self.a.with_number(F::Int::from(3)) {
+            self.add_a_minus_three(&toadd)
+        } else {
+            self.add_any_a(&toadd)
+        }
+    }
+}
+
+// same affine point - X1 Z2 = X2 Z1 and Y1 Z2 = Y2 Z1 - rather than same coordinates
+impl<F: FieldElement> PartialEq for ProjectivePoint<F> {
+    fn eq(&self, other: &Self) -> bool {
+        if self.a != other.a || self.b != other.b {
+            return false;
+        }
+        if self.is_infinity() || other.is_infinity() {
+            return self.is_infinity() && other.is_infinity();
+        }
+        self.x * other.z == other.x * self.z && self.y * other.z == other.y * self.z
+    }
+}
@@ -0,0 +1,113 @@
+use crate::fpelem::{count_bits_generic_uint, generic_uint_to_be_bytes, GenericUInt};
+use crate::hashing::{bits2int, bits2octets, HashAlgorithm};
+use hmac::digest::core_api::BlockSizeUser;
+use hmac::digest::Digest;
+use hmac::{Mac, SimpleHmac};
+use sha2::{Sha224, Sha256, Sha384, Sha512};
+use std::marker::PhantomData;
+
+// Deterministic nonces as in RFC 6979 3.2 - an HMAC_DRBG keyed on the private key and the hash.
+// new takes the hash as the already reduced z = bits2int(H(m)) mod q, which is exactly what
+// bits2octets would hand to the DRBG anyway, from_digest takes H(m) itself.
+// Each call to next_k gives the next candidate in [1, q-1], so if a k is no good for signing
+// (r or s came out as 0) just ask for another one.
+pub struct Rfc6979<D, T> {
+    k: Vec<u8>,
+    v: Vec<u8>,
+    order: T,
+    qlen: u64,
+    started: bool,
+    digest: PhantomData<D>,
+}
+
+impl<D: Digest + BlockSizeUser, T: GenericUInt> Rfc6979<D, T> {
+    pub fn new(privkey: T, hash: T, order: T) -> Self {
+        let rlen = count_bits_generic_uint(order).div_ceil(8) as usize;
+        Self::seeded(privkey, generic_uint_to_be_bytes(hash % order, rlen), order)
+    }
+
+    pub fn from_digest(privkey: T, digest: &[u8], order: T) -> Self {
+        Self::seeded(privkey, bits2octets(digest, order), order)
+    }
+
+    // h1 is bits2octets(H(m)), already ceil(qlen/8) bytes
+    fn seeded(privkey: T, h1: Vec<u8>, order: T) -> Self {
+        let qlen = count_bits_generic_uint(order);
+        let rlen = qlen.div_ceil(8) as usize;
+        let hlen = <D as Digest>::output_size();
+        let mut drbg = Rfc6979 {
+            k: vec![0x00; hlen],
+            v: vec![0x01; hlen],
+            order,
+            qlen,
+            started: false,
+            digest: PhantomData,
+        };
+        // int2octets(x) || bits2octets(h1)
+        let mut seed = generic_uint_to_be_bytes(privkey, rlen);
+        seed.extend(h1);
+        for sep in [0x00, 0x01] {
+            drbg.k = drbg.hmac(&[&drbg.v, &[sep], &seed]);
+            drbg.v = drbg.hmac(&[&drbg.v]);
+        }
+        drbg
+    }
+
+    fn hmac(&self, parts: &[&[u8]]) -> Vec<u8> {
+        let mut mac =
+            SimpleHmac::<D>::new_from_slice(&self.k).expect("HMAC accepts keys of any length");
+        for part in parts {
+            mac.update(part);
+        }
+        mac.finalize().into_bytes().to_vec()
+    }
+
+    pub fn next_k(&mut self) -> T {
+        let zero = T::from(0);
+        loop {
+            // step h.3 - only reseed once a candidate has been handed out or rejected
+            if self.started {
+                self.k = self.hmac(&[&self.v, &[0x00]]);
+                self.v = self.hmac(&[&self.v]);
+            }
+            self.started = true;
+            let mut t = Vec::new();
+            while (8 * t.len() as u64) < self.qlen {
+                self.v = self.hmac(&[&self.v]);
+                t.extend_from_slice(&self.v);
+            }
+            let k: T = bits2int(&t, self.qlen);
+            if k != zero && k < self.order {
+                return k;
+            }
+        }
+    }
+}
+
+// Rfc6979 with the HMAC hash picked at runtime, for KeyChain's HashAlgorithm setting
+pub(crate) enum DynRfc6979<T> {
+    Sha224(Rfc6979<Sha224, T>),
+    Sha256(Rfc6979<Sha256, T>),
+    Sha384(Rfc6979<Sha384, T>),
+    Sha512(Rfc6979<Sha512, T>),
+}
+
+impl<T: GenericUInt> DynRfc6979<T> {
+    pub(crate) fn new(hash_algorithm: HashAlgorithm, privkey: T, hash: T, order: T) -> Self {
+        match hash_algorithm {
+            HashAlgorithm::Sha224 => DynRfc6979::Sha224(Rfc6979::new(privkey, hash, order)),
+            HashAlgorithm::Sha256 => DynRfc6979::Sha256(Rfc6979::new(privkey, hash, order)),
+            HashAlgorithm::Sha384 => DynRfc6979::Sha384(Rfc6979::new(privkey, hash, order)),
+            HashAlgorithm::Sha512 => DynRfc6979::Sha512(Rfc6979::new(privkey, hash, order)),
+        }
+    }
+
+    pub(crate) fn next_k(&mut self) -> T {
+        match self {
+            DynRfc6979::Sha224(nonces) => nonces.next_k(),
+            DynRfc6979::Sha256(nonces) => nonces.next_k(),
+            DynRfc6979::Sha384(nonces) => nonces.next_k(),
+            DynRfc6979::Sha512(nonces) => nonces.next_k(),
+        }
+    }
+}
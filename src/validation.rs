@@ -0,0 +1,343 @@
+use crate::curves::CurveDomain;
+use crate::ellipticcurve::ECPoint;
+use crate::fpelem::{
+    count_bits_generic_uint, generic_uint_from_be_bytes, get_generic_uint_below, pow_mod, FpElem,
+    GenericUInt, Pow,
+};
+use rand::{thread_rng, CryptoRng, RngCore};
+use std::fmt;
+use std::fmt::Display;
+
+// Outcome of a single check - Unknown is for things this crate can't settle, e.g. the curve order
+// of a mid sized curve, or a twist order with two large prime factors
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Check {
+    Pass,
+    Fail,
+    Unknown,
+}
+
+// smallest k with p^k = 1 mod n, only searched up to MOV_DEGREE_BOUND
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum EmbeddingDegree {
+    Exactly(u64),
+    AtLeast(u64),
+}
+
+// SEC1 3.1.1.2.1 asks for p^B != 1 mod n for all 1 <= B < 100
+const MOV_DEGREE_BOUND: u64 = 100;
+// SafeCurves wants rho to cost at least 2^100 on the twist, i.e. a prime factor of ~200 bits
+const TWIST_RHO_BITS: u64 = 200;
+// twist orders are trial divided by everything below this before testing what's left for primality
+const TRIAL_DIVISION_BOUND: u32 = 1 << 16;
+// below this the curve order is found by just counting points
+const POINT_COUNT_BOUND: u32 = 1 << 16;
+// random Miller-Rabin rounds run on top of the fixed small prime bases
+const MILLER_RABIN_ROUNDS: usize = 8;
+
+// What CurveDomain::validate found. Each Check is Pass when the domain is fine on that count.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ValidationReport<T> {
+    pub name: &'static str,
+    // 4a^3 + 27b^2 != 0 mod p, else the "curve" is singular
+    pub discriminant: Check,
+    pub p_prime: Check,
+    pub n_prime: Check,
+    // G is a point on the domain's curve other than infinity and n * G = infinity
+    pub generator_order: Check,
+    // #E(F_p) - the unique multiple of n in the Hasse interval when n > 4 sqrt(p) (and T has two
+    // bits to spare above p), or counted directly on tiny fields, None otherwise
+    pub curve_order: Option<T>,
+    // cofactor * n == #E(F_p)
+    pub cofactor: Check,
+    pub embedding_degree: EmbeddingDegree,
+    // the embedding degree is at least MOV_DEGREE_BOUND, so the MOV/Frey-Ruck reduction to a
+    // finite field doesn't help
+    pub mov: Check,
+    // n != p and #E(F_p) != p, so Smart's attack doesn't apply
+    pub anomalous: Check,
+    // #E'(F_p) = 2(p + 1) - #E(F_p) for the quadratic twist
+    pub twist_order: Option<T>,
+    // bit length of the largest prime factor of the twist order
+    pub twist_largest_prime_bits: Option<u64>,
+    // rho on the twist costs at least 2^100, for invalid curve attacks on x-only ladders
+    pub twist: Check,
+}
+
+impl<T> ValidationReport<T> {
+    fn checks(&self) -> [(&'static str, Check); 8] {
+        [
+            ("discriminant 4a^3 + 27b^2 != 0", self.discriminant),
+            ("p is prime", self.p_prime),
+            ("n is prime", self.n_prime),
+            ("n * G = infinity", self.generator_order),
+            ("cofactor * n = #E", self.cofactor),
+            ("embedding degree >= 100 (MOV)", self.mov),
+            ("not anomalous (Smart)", self.anomalous),
+            ("twist rho >= 2^100", self.twist),
+        ]
+    }
+
+    // every check passed - Unknown doesn't count
+    pub fn is_safe(&self) -> bool {
+        self.checks().iter().all(|(_, check)| *check == Check::Pass)
+    }
+}
+
+impl Display for Check {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Check::Pass => write!(f, "pass"),
+            Check::Fail => write!(f, "FAIL"),
+            Check::Unknown => write!(f, "unknown"),
+        }
+    }
+}
+
+impl Display for EmbeddingDegree {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EmbeddingDegree::Exactly(k) => write!(f, "{}", k),
+            EmbeddingDegree::AtLeast(k) => write!(f, ">= {}", k),
+        }
+    }
+}
+
+// one line per check, with the numbers behind the ones that have them
+impl<T: Display> Display for ValidationReport<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let curve_order = match &self.curve_order {
+            Some(order) => format!("#E = {}", order),
+            None => "#E not determined".to_string(),
+        };
+        let twist = match self.twist_largest_prime_bits {
+            Some(bits) => format!("largest twist prime factor {} bits", bits),
+            None => "twist order not factored".to_string(),
+        };
+        // in the same order as checks()
+        let details = [
+            String::new(),
+            String::new(),
+            String::new(),
+            String::new(),
+            curve_order,
+            format!("embedding degree {}", self.embedding_degree),
+            String::new(),
+            twist,
+        ];
+        writeln!(f, "{}", self.name)?;
+        for ((label, check), detail) in self.checks().iter().zip(details) {
+            writeln!(f, "  {:<32} {:<8} {}", label, check, detail)?;
+        }
+        write!(f, "  safe: {}", if self.is_safe() { "yes" } else { "no" })
+    }
+}
+
+fn check(ok: bool) -> Check {
+    if ok {
+        Check::Pass
+    } else {
+        Check::Fail
+    }
+}
+
+// Miller-Rabin with the primes up to 37 as bases, which is exact below 3.3 * 10^24, and then
+// MILLER_RABIN_ROUNDS random ones so a composite built to fool the fixed bases still gets caught
+fn is_probable_prime<T: GenericUInt, R: RngCore + CryptoRng + ?Sized>(n: T, rng: &mut R) -> bool {
+    let zero = T::from(0);
+    let one = T::from(1);
+    let two = T::from(2);
+    let fixed = [2u8, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37];
+    if n < two {
+        return false;
+    }
+    for base in fixed {
+        if n % T::from(base) == zero {
+            return n == T::from(base);
+        }
+    }
+    // n - 1 = d * 2^s with d odd
+    let nm1 = n - one;
+    let mut d = nm1;
+    let mut s = 0;
+    while d & one == zero {
+        d = d >> one;
+        s += 1;
+    }
+    let witness = |a: T| -> bool {
+        let mut x = pow_mod(a, d, n);
+        if x == one || x == nm1 {
+            return false;
+        }
+        for _ in 1..s {
+            x = pow_mod(x, two, n);
+            if x == nm1 {
+                return false;
+            }
+        }
+        true
+    };
+    // n > 37 here so the random bases in [2, n - 2] are well defined
+    !fixed.iter().any(|base| witness(T::from(*base)))
+        && !(0..MILLER_RABIN_ROUNDS)
+            .any(|_| witness(get_generic_uint_below(n - T::from(3), rng) + two))
+}
+
+// floor(sqrt(x)) by Newton's method
+pub(crate) fn isqrt<T: GenericUInt>(x: T) -> T {
+    let one = T::from(1);
+    if x <= one {
+        return x;
+    }
+    let mut root = x;
+    let mut next = (root + one) >> one;
+    while next < root {
+        root = next;
+        next = (root + x / root) >> one;
+    }
+    root
+}
+
+impl<T: GenericUInt> CurveDomain<T> {
+    // the checks in ValidationReport, using the thread rng for the Miller-Rabin bases
+    pub fn validate(&self) -> ValidationReport<T> {
+        self.validate_with_rng(&mut thread_rng())
+    }
+
+    // Slow on real sized curves - this is a scalar multiplication and a few dozen modular
+    // exponentiations, and trial division of the twist order.
+    pub fn validate_with_rng<R: RngCore + CryptoRng + ?Sized>(
+        &self,
+        rng: &mut R,
+    ) -> ValidationReport<T> {
+        let zero = T::from(0);
+        let one = T::from(1);
+        let two = T::from(2);
+        let (p, n) = (self.p, self.n());
+        let (a, b) = (FpElem::new(self.a, p), FpElem::new(self.b, p));
+
+        let discriminant = a.pow(T::from(3)) * T::from(4) + b.pow(two) * T::from(27);
+        let p_prime = is_probable_prime(p, rng);
+        let n_prime = is_probable_prime(n, rng);
+        let g = self.generator();
+        let generator_order = g.position.is_some()
+            && g.same_curve(&ECPoint::new_infinity(self.a, self.b, p))
+            && g.is_on_curve()
+            && (g * n).position.is_none();
+
+        // with n prime and G of order n, n divides #E, and once n > 4 sqrt(p) the Hasse
+        // interval [p + 1 - 2 sqrt(p), p + 1 + 2 sqrt(p)] only has room for one multiple of n.
+        // That and the twist order 2(p + 1) - #E need T to have two bits to spare above p
+        let root = isqrt(p) + one;
+        let headroom = (p << two) >> two == p;
+        let curve_order = if headroom && n_prime && generator_order && n > T::from(4) * root {
+            let upper = p + one + two * root;
+            let candidate = (upper / n) * n;
+            // candidate >= p + 1 - 2 sqrt(p), written so it can't underflow
+            if candidate + two * root >= p + one {
+                Some(candidate)
+            } else {
+                None
+            }
+        } else if p < generic_uint_from_be_bytes(&POINT_COUNT_BOUND.to_be_bytes()) {
+            Some(self.count_points())
+        } else {
+            None
+        };
+        let cofactor = match curve_order {
+            Some(order) => check(self.cofactor * n == order),
+            None => Check::Unknown,
+        };
+
+        let mut embedding_degree = EmbeddingDegree::AtLeast(MOV_DEGREE_BOUND);
+        let base = FpElem::new(p, n);
+        let mut power = base;
+        for k in 1..MOV_DEGREE_BOUND {
+            if power.number == one {
+                embedding_degree = EmbeddingDegree::Exactly(k);
+                break;
+            }
+            power *= base;
+        }
+        let mov = check(matches!(embedding_degree, EmbeddingDegree::AtLeast(_)));
+
+        let anomalous = check(n != p && curve_order != Some(p));
+
+        let twist_order = curve_order.map(|order| two * (p + one) - order);
+        let twist_largest_prime_bits = match twist_order {
+            Some(order) if order != zero => largest_prime_factor_bits(order, rng),
+            _ => None,
+        };
+        let twist = match twist_largest_prime_bits {
+            Some(bits) => check(bits >= TWIST_RHO_BITS),
+            None => Check::Unknown,
+        };
+
+        ValidationReport {
+            name: self.name,
+            discriminant: check(discriminant.number != zero),
+            p_prime: check(p_prime),
+            n_prime: check(n_prime),
+            generator_order: check(generator_order),
+            curve_order,
+            cofactor,
+            embedding_degree,
+            mov,
+            anomalous,
+            twist_order,
+            twist_largest_prime_bits,
+            twist,
+        }
+    }
+
+    // #E(F_p) = 1 + sum over x of (1 + legendre(x^3 + ax + b)), only sensible for tiny p
+    fn count_points(&self) -> T {
+        let one = T::from(1);
+        let p = self.p;
+        let (a, b) = (FpElem::new(self.a, p), FpElem::new(self.b, p));
+        let mut count = one;
+        let mut x = T::from(0);
+        while x < p {
+            let xe = FpElem::new(x, p);
+            let rhs = xe.pow(T::from(3)) + a * xe + b;
+            count = match rhs.legendre() {
+                1 => count + T::from(2),
+                0 => count + one,
+                _ => count,
+            };
+            x = x + one;
+        }
+        count
+    }
+}
+
+// trial division up to TRIAL_DIVISION_BOUND, then None if what's left is composite as neither
+// of its (large) factors can be found this way
+fn largest_prime_factor_bits<T: GenericUInt, R: RngCore + CryptoRng + ?Sized>(
+    x: T,
+    rng: &mut R,
+) -> Option<u64> {
+    let zero = T::from(0);
+    let one = T::from(1);
+    let mut rest = x;
+    let mut largest = one;
+    // 2 then the odd numbers, d and divisor kept in step
+    let mut divisor = 2;
+    let mut d = T::from(2);
+    while divisor < TRIAL_DIVISION_BOUND {
+        while rest % d == zero {
+            rest = rest / d;
+            largest = d;
+        }
+        let step = if divisor == 2 { 1 } else { 2 };
+        divisor += step as u32;
+        d = d + T::from(step);
+    }
+    if rest == one {
+        return Some(count_bits_generic_uint(largest));
+    }
+    if is_probable_prime(rest, rng) {
+        return Some(count_bits_generic_uint(rest));
+    }
+    None
+}
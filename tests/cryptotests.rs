@@ -1,7 +1,14 @@
 use rustecdsg::FpElem;
 extern crate primitive_types;
 use primitive_types::U512;
-use rustecdsg::{ECPoint, KeyChain, Signature};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use rustecdsg::{
+    digest_to_scalar, BatchVerifyError, CurveDomain, CurveError, ECPoint, HashAlgorithm, KeyChain,
+    KeyChainError, NonceMode, RecoverableSignature, RecoveryError, Rfc6979, Signature, VerifyError,
+};
+use sha2::{Digest, Sha224, Sha256, Sha384, Sha512};
+use std::time::Instant;
 
 #[test]
 fn signature_verify() {
@@ -11,17 +18,12 @@ fn signature_verify() {
     let px = U512::from("0x04519fac3d910ca7e7138f7013706f619fa8f033e6ec6e09370ea38cee6a7574");
     let py = U512::from("0x82b51eab8c27c66e26c858a079bcdf4f1ada34cec420cafc7eac1a42216fb6c4");
 
-    let gx = U512::from("0x79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798");
-    let gy = U512::from("0x483ada7726a3c4655da4fbfc0e1108a8fd17b448a68554199c47d08ffb10d4b8");
-    let n = U512::from("0xfffffffffffffffffffffffffffffffebaaedce6af48a03bbfd25e8cd0364141");
-
-    let p = U512::from("0xfffffffffffffffffffffffffffffffffffffffffffffffffffffffefffffc2f");
-
-    // secp256k1 generating point
-    let g: ECPoint<FpElem<U512>> = ECPoint::new(gx, gy, U512::from(0), U512::from(7), p);
+    // secp256k1
+    let domain = CurveDomain::<U512>::secp256k1();
+    let n = domain.n();
 
     // Create the public key point P
-    let pubkeypoint = ECPoint::new(px, py, U512::from(0), U512::from(7), p);
+    let pubkeypoint = ECPoint::new(px, py, domain.a, domain.b, domain.p);
 
     // Create FpElem instances for z, r, s with modulus n
     let z = FpElem::new(z, n);
@@ -32,9 +34,9 @@ fn signature_verify() {
     let sig = Signature::new(r, s);
 
     // Create keychain with public key
-    let keychain = KeyChain::new_pub(pubkeypoint, (g, n));
+    let keychain = KeyChain::new_pub(pubkeypoint, domain);
 
-    let is_valid = keychain.verify_sig(&z, &sig);
+    let is_valid = keychain.verify_sig(&z, &sig).is_ok();
     println!("Signature verification result: {}", is_valid);
     // Verify the signature
     assert!(is_valid);
@@ -43,17 +45,12 @@ fn signature_verify() {
 #[test]
 fn sign_hash() {
     // secp256k1 parameters
-    let gx = U512::from("0x79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798");
-    let gy = U512::from("0x483ada7726a3c4655da4fbfc0e1108a8fd17b448a68554199c47d08ffb10d4b8");
-    let n = U512::from("0xfffffffffffffffffffffffffffffffebaaedce6af48a03bbfd25e8cd0364141");
-    let p = U512::from("0xfffffffffffffffffffffffffffffffffffffffffffffffffffffffefffffc2f");
-
-    // Create generator point
-    let g: ECPoint<FpElem<U512>> = ECPoint::new(gx, gy, U512::from(0), U512::from(7), p);
+    let domain = CurveDomain::<U512>::secp256k1();
+    let n = domain.n();
 
     // Create private key and KeyChain
     let priv_key = FpElem::new(U512::from(123456789), n); // Example private key
-    let keychain = KeyChain::new_priv(priv_key, (g, n));
+    let keychain = KeyChain::new_priv(priv_key, domain);
 
     // Message hash to sign
     let z = FpElem::new(
@@ -65,7 +62,7 @@ fn sign_hash() {
     let sig = keychain.sign(&z).unwrap();
 
     // Verify the signature with the public key
-    let is_valid = keychain.verify_sig(&z, &sig);
+    let is_valid = keychain.verify_sig(&z, &sig).is_ok();
     println!("Generated signature verification result: {}", is_valid);
     println!("Signature r: {}", sig.targetx);
     println!("Signature s: {}", sig.sig);
@@ -73,3 +70,543 @@ fn sign_hash() {
 
     assert!(is_valid);
 }
+
+fn sha256_int(msg: &[u8]) -> U512 {
+    U512::from_big_endian(&Sha256::digest(msg))
+}
+
+#[test]
+fn rfc6979_nonces() {
+    // RFC 6979 A.2.5, P-256 with SHA-256
+    let n = CurveDomain::<U512>::p256().n();
+    let x = U512::from("0xc9afa9d845ba75166b5c215767b1d6934e50c3db36e89b127b8a622b120f6721");
+    // format: (message, expected k)
+    let vectors = [
+        (
+            "sample",
+            "0xa6e3c57dd01abe90086538398355dd4c3b17aa873382b0f24d6129493d8aad60",
+        ),
+        (
+            "test",
+            "0xd16b6ae827f17175e040871a1c7ec3500192c4c92677336ec2537acaee0008e0",
+        ),
+    ];
+    for (msg, k) in vectors.iter() {
+        let mut nonces = Rfc6979::<Sha256, U512>::new(x, sha256_int(msg.as_bytes()), n);
+        assert_eq!(nonces.next_k(), U512::from(*k));
+    }
+
+    // secp256k1 with SHA-256, the widely used vectors from the python-ecdsa/trezor test suites
+    let n = CurveDomain::<U512>::secp256k1().n();
+    // format: (private key, message, expected k)
+    let vectors = [
+        (
+            U512::from(1),
+            "Satoshi Nakamoto",
+            "0x8f8a276c19f4149656b280621e358cce24f5f52542772691ee69063b74f15d15",
+        ),
+        (
+            U512::from(1),
+            "All those moments will be lost in time, like tears in rain. Time to die...",
+            "0x38aa22d72376b4dbc472e06c3ba403ee0a394da63fc58d88686c611aba98d6b3",
+        ),
+        (
+            n - U512::from(1),
+            "Satoshi Nakamoto",
+            "0x33a19b60e25fb6f4435af53a3d42d493644827367e6453928554f43e49aa6f90",
+        ),
+    ];
+    for (x, msg, k) in vectors.iter() {
+        let mut nonces = Rfc6979::<Sha256, U512>::new(*x, sha256_int(msg.as_bytes()), n);
+        assert_eq!(nonces.next_k(), U512::from(*k));
+    }
+}
+
+#[test]
+fn rfc6979_sign_p256() {
+    let domain = CurveDomain::p256();
+    let n = domain.n();
+    let x = U512::from("0xc9afa9d845ba75166b5c215767b1d6934e50c3db36e89b127b8a622b120f6721");
+    let keychain =
+        KeyChain::new_priv(FpElem::new(x, n), domain).with_nonce_mode(NonceMode::Rfc6979);
+    let z = FpElem::new(sha256_int(b"sample"), n);
+
+    let sig = keychain.sign(&z).unwrap();
+    let r = U512::from("0xefd48b2aacb6a8fd1140dd9cd45e81d69d2c877b56aaf991c34d0ea84eaf3716");
+    let s = U512::from("0xf7cb1c942d657c41d436c7a1b6e29f65f3e900dbb9aff4064dc4ab2f843acda8");
+    assert_eq!((sig.targetx.number, sig.sig.number), (r, s));
+    assert!(keychain.verify_sig(&z, &sig).is_ok());
+}
+
+#[test]
+fn rfc6979_sign_secp256k1() {
+    let domain = CurveDomain::secp256k1();
+    let n = domain.n();
+    let keychain = KeyChain::new_priv(FpElem::new(U512::from(1), n), domain)
+        .with_nonce_mode(NonceMode::Rfc6979)
+        .with_low_s(true);
+    let z = FpElem::new(sha256_int(b"Satoshi Nakamoto"), n);
+
+    let sig = keychain.sign(&z).unwrap();
+    let r = U512::from("0x934b1ea10a4b3c1757e2b0c017d0b6143ce3c9a7e6a4a49860d7a6ab210ee3d8");
+    // the published s is low-S normalised
+    let s = U512::from("0x2442ce9d2b916064108014783e923ec36b49743e2ffa1c4496f01a512aafd9e5");
+    assert_eq!((sig.targetx.number, sig.sig.number), (r, s));
+
+    // same key and hash, same signature
+    let again = keychain.sign(&z).unwrap();
+    assert_eq!(again, sig);
+}
+
+// y^2 = x^3 + 2x + 2 over F_17 has 19 points, so every point other than infinity generates it
+fn toy_curve() -> CurveDomain<u64> {
+    CurveDomain::new("toy", ECPoint::new(5u64, 1u64, 2u64, 2u64, 17u64), 19, 1).unwrap()
+}
+
+#[test]
+fn sign_every_hash_toy_curve() {
+    let domain = toy_curve();
+    let n = domain.n();
+    let keychain = KeyChain::new_priv(FpElem::new(7, n), domain);
+    let mut rng = StdRng::seed_from_u64(6979);
+    // includes the zero hash, which used to panic
+    for z in 0..n {
+        let z = FpElem::new(z, n);
+        let sig = keychain.sign_with_rng(&z, &mut rng).unwrap();
+        assert!(sig.targetx.number >= 1 && sig.targetx.number < n);
+        assert!(keychain.verify_sig(&z, &sig).is_ok());
+    }
+
+    // same seed, same signature
+    let z = FpElem::new(3, n);
+    let first = keychain
+        .sign_with_rng(&z, &mut StdRng::seed_from_u64(1))
+        .unwrap();
+    let second = keychain
+        .sign_with_rng(&z, &mut StdRng::seed_from_u64(1))
+        .unwrap();
+    assert_eq!((first.targetx, first.sig), (second.targetx, second.sig));
+}
+
+#[test]
+fn sign_gives_up_on_bad_nonces() {
+    // deliberately wrong order of 2 for a point with even x, so the only nonce k = 1 always gives r = 0
+    let g = ECPoint::new(6u64, 3u64, 2u64, 2u64, 17u64);
+    let domain = CurveDomain::new("toy", g, 2u64, 1).unwrap();
+    let keychain = KeyChain::new_priv(FpElem::new(1, 2), domain.clone());
+    let z = FpElem::new(1, 2);
+    for mode in [NonceMode::Random, NonceMode::Rfc6979] {
+        let keychain = KeyChain::new_priv(FpElem::new(1, 2), domain.clone()).with_nonce_mode(mode);
+        assert!(matches!(
+            keychain.sign_with_rng(&z, &mut StdRng::seed_from_u64(0)),
+            Err(KeyChainError::NonceRetriesExhausted(_))
+        ));
+    }
+    assert!(matches!(
+        KeyChain::new_pub(keychain.pubkey, domain).sign(&z),
+        Err(KeyChainError::NoPrivateKey)
+    ));
+}
+
+#[test]
+fn generate_keys_toy_curve() {
+    let domain = toy_curve();
+    let n = domain.n();
+    let mut rng = StdRng::seed_from_u64(4);
+    for _ in 0..20 {
+        let keychain = KeyChain::generate(domain.clone(), &mut rng).unwrap();
+        assert!(keychain.pubkey.position.is_some());
+        let z = FpElem::new(11, n);
+        let sig = keychain.sign_with_rng(&z, &mut rng).unwrap();
+        assert!(KeyChain::new_pub(keychain.pubkey, domain.clone())
+            .verify_sig(&z, &sig)
+            .is_ok());
+    }
+
+    // same seed, same key
+    let first = KeyChain::generate(domain.clone(), &mut StdRng::seed_from_u64(9)).unwrap();
+    let second = KeyChain::generate(domain.clone(), &mut StdRng::seed_from_u64(9)).unwrap();
+    assert_eq!(first.pubkey, second.pubkey);
+
+    // a generator at infinity can only ever give an infinite public key, CurveDomain::new won't
+    // take one
+    let inf = ECPoint::new_infinity(2u64, 2u64, 17u64);
+    assert_eq!(
+        CurveDomain::new("toy", inf, n, 1),
+        Err(CurveError::GeneratorAtInfinity)
+    );
+    // but it can't check n, and (0, 0) on y^2 = x^3 + 3x over F_17 has order 2 rather than 3, so
+    // the private key 2 gives infinity
+    let bogus: ECPoint<FpElem<u64>> = ECPoint::new(0u64, 0u64, 3u64, 0u64, 17u64);
+    let bogus = CurveDomain::new("bogus", bogus, 3, 1).unwrap();
+    assert!((0..20).any(|seed| matches!(
+        KeyChain::generate(bogus.clone(), &mut StdRng::seed_from_u64(seed)),
+        Err(KeyChainError::PublicKeyAtInfinity)
+    )));
+}
+
+#[test]
+fn low_s_toy_curve() {
+    let domain = toy_curve();
+    let n = domain.n();
+    let mut rng = StdRng::seed_from_u64(5);
+    let keychain = KeyChain::new_priv(FpElem::new(7, n), domain.clone());
+    let low = KeyChain::new_priv(FpElem::new(7, n), domain.clone()).with_low_s(true);
+    let strict = KeyChain::new_pub(keychain.pubkey, domain).with_strict_verification(true);
+    let mut high_seen = false;
+    for z in 0..n {
+        let z = FpElem::new(z, n);
+        let sig = keychain.sign_with_rng(&z, &mut rng).unwrap();
+        let normalized = sig.normalize_s();
+        assert!(normalized.is_low_s());
+        assert_eq!(normalized.targetx, sig.targetx);
+        assert!(keychain.verify_sig(&z, &normalized).is_ok());
+        assert!(strict.verify_sig(&z, &normalized).is_ok());
+        // a high-S signature is still fine normally but strict mode turns it away
+        if !sig.is_low_s() {
+            high_seen = true;
+            assert_eq!(sig.sig.number + normalized.sig.number, n);
+            assert!(keychain.verify_sig(&z, &sig).is_ok());
+            assert_eq!(strict.verify_sig(&z, &sig), Err(VerifyError::HighS));
+        }
+        assert!(low.sign_with_rng(&z, &mut rng).unwrap().is_low_s());
+    }
+    assert!(high_seen);
+}
+
+#[test]
+fn verify_rejects_malformed_input() {
+    let domain = toy_curve();
+    let n = domain.n();
+    let keychain = KeyChain::new_priv(FpElem::new(7, n), domain.clone());
+    let z = FpElem::new(5, n);
+    let sig = keychain
+        .sign_with_rng(&z, &mut StdRng::seed_from_u64(6))
+        .unwrap();
+    assert!(keychain.verify_sig(&z, &sig).is_ok());
+    // the fields are pub, so nothing stops a number at or above n
+    let mut unreduced = FpElem::new(0, n);
+    unreduced.number = n + 1;
+
+    // format: (signature, expected error)
+    let bad = [
+        (
+            Signature::new(FpElem::new(0, n), sig.sig),
+            VerifyError::ROutOfRange,
+        ),
+        (
+            Signature::new(sig.targetx, FpElem::new(0, n)),
+            VerifyError::SOutOfRange,
+        ),
+        (
+            Signature::new(sig.targetx, unreduced),
+            VerifyError::SOutOfRange,
+        ),
+        (
+            Signature::new(sig.targetx, FpElem::new(1, 23)),
+            VerifyError::WrongModulus,
+        ),
+        (
+            Signature::new(sig.targetx, sig.sig + FpElem::new(1, n)),
+            VerifyError::Mismatch,
+        ),
+    ];
+    for (sig, err) in bad.iter() {
+        assert_eq!(keychain.verify_sig(&z, sig), Err(err.clone()));
+    }
+
+    // format: (public key, expected error)
+    let bad_keys = [
+        (
+            ECPoint::new_infinity(2u64, 2u64, 17u64),
+            VerifyError::PubKeyAtInfinity,
+        ),
+        (
+            ECPoint::new(5u64, 2u64, 2u64, 2u64, 17u64),
+            VerifyError::PubKeyNotOnCurve,
+        ),
+        // on y^2 = x^3 + 2x + 3 instead
+        (
+            ECPoint::new(2u64, 7u64, 2u64, 3u64, 17u64),
+            VerifyError::PubKeyNotOnCurve,
+        ),
+    ];
+    for (pubkey, err) in bad_keys.iter() {
+        let verifier = KeyChain::new_pub(*pubkey, domain.clone());
+        assert_eq!(verifier.verify_sig(&z, &sig), Err(err.clone()));
+    }
+}
+
+#[test]
+fn verify_rejects_small_subgroup_key() {
+    // y^2 = x^3 + 7 over F_223 has 252 points, (47, 71) generates a subgroup of order 21 and
+    // (6, 0) has order 2 so sits outside it
+    let g = ECPoint::new(47u64, 71u64, 0u64, 7u64, 223u64);
+    let domain = CurveDomain::new("F_223", g, 21u64, 12).unwrap();
+    let twotorsion = ECPoint::new(6u64, 0u64, 0u64, 7u64, 223u64);
+    let keychain = KeyChain::new_pub(twotorsion, domain);
+    let sig = Signature::new(FpElem::new(1, 21), FpElem::new(1, 21));
+    assert_eq!(
+        keychain.verify_sig(&FpElem::new(1, 21), &sig),
+        Err(VerifyError::PubKeyNotInSubgroup)
+    );
+}
+
+#[test]
+fn recover_pubkey_toy_curve() {
+    let domain = toy_curve();
+    let n = domain.n();
+    let mut rng = StdRng::seed_from_u64(7);
+    for low_s in [false, true] {
+        let keychain = KeyChain::generate(domain.clone(), &mut rng)
+            .unwrap()
+            .with_low_s(low_s);
+        for z in 0..n {
+            let z = FpElem::new(z, n);
+            let sig = keychain.sign_recoverable_with_rng(&z, &mut rng).unwrap();
+            assert!(keychain.verify_sig(&z, &sig.signature).is_ok());
+            let recovered = KeyChain::recover_pubkey(&domain, &z, &sig).unwrap();
+            assert_eq!(recovered, keychain.pubkey);
+            // the other parity is the other R, so a different key if it gives one at all
+            let flipped = RecoverableSignature::new(sig.signature, sig.recovery_id ^ 1);
+            assert_ne!(
+                KeyChain::recover_pubkey(&domain, &z, &flipped),
+                Ok(keychain.pubkey)
+            );
+        }
+    }
+
+    let z = FpElem::new(1, n);
+    let sig = Signature::new(FpElem::new(3, n), FpElem::new(4, n));
+    assert_eq!(
+        KeyChain::recover_pubkey(&domain, &z, &RecoverableSignature::new(sig, 4)),
+        Err(RecoveryError::InvalidRecoveryId(4))
+    );
+    // n > p here, so no R can have had its x wrap around
+    assert_eq!(
+        KeyChain::recover_pubkey(&domain, &z, &RecoverableSignature::new(sig, 2)),
+        Err(RecoveryError::NoPointForR)
+    );
+}
+
+#[test]
+fn recover_pubkey_secp256k1() {
+    // private key 1, so the public key is G itself
+    let domain = CurveDomain::secp256k1();
+    let n = domain.n();
+    let keychain = KeyChain::new_priv(FpElem::new(U512::from(1), n), domain.clone())
+        .with_nonce_mode(NonceMode::Rfc6979)
+        .with_low_s(true);
+    let z = FpElem::new(sha256_int(b"Satoshi Nakamoto"), n);
+    let sig = keychain.sign_recoverable(&z).unwrap();
+    assert_eq!(sig.signature, keychain.sign(&z).unwrap());
+    assert_eq!(
+        KeyChain::recover_pubkey(&domain, &z, &sig),
+        Ok(domain.generator())
+    );
+}
+
+// y^2 = x^3 + 5 over F_1000003 has prime order 999007, big enough that random batch
+// coefficients won't cancel by accident but still quick with u64
+fn medium_curve() -> CurveDomain<u64> {
+    let g = ECPoint::new(1u64, 586770u64, 0u64, 5u64, 1000003u64);
+    CurveDomain::new("medium", g, 999007, 1).unwrap()
+}
+
+#[test]
+fn verify_batch_medium_curve() {
+    let domain = medium_curve();
+    let n = domain.n();
+    let mut rng = StdRng::seed_from_u64(8);
+    let digests: Vec<_> = (0..30u8).map(|i| Sha256::digest([i]).to_vec()).collect();
+    let mut entries = Vec::new();
+    for (i, digest) in digests.iter().enumerate() {
+        let keychain = KeyChain::generate(domain.clone(), &mut rng).unwrap();
+        let hash = digest_to_scalar(digest, n);
+        let sig = keychain.sign_recoverable_with_rng(&hash, &mut rng).unwrap();
+        // every fifth entry without its recovery id, so it's checked on its own
+        let recovery_id = if i % 5 == 0 {
+            None
+        } else {
+            Some(sig.recovery_id)
+        };
+        entries.push((
+            keychain.pubkey,
+            digest.as_slice(),
+            sig.signature,
+            recovery_id,
+        ));
+    }
+    let verifier = KeyChain::new_pub(entries[0].0, domain.clone());
+    assert_eq!(verifier.verify_batch(&entries, &mut rng), Ok(()));
+    assert_eq!(verifier.verify_batch(&[], &mut rng), Ok(()));
+    // every entry agrees with verify_prehash
+    for (pubkey, digest, sig, _) in entries.iter() {
+        let keychain = KeyChain::new_pub(*pubkey, domain.clone());
+        assert_eq!(keychain.verify_prehash(digest, sig), Ok(()));
+    }
+    // a wrong recovery id, or one out of range, doesn't make a valid signature invalid
+    entries[7].3 = entries[7].3.map(|id| id ^ 1);
+    entries[8].3 = Some(4);
+    assert_eq!(verifier.verify_batch(&entries, &mut rng), Ok(()));
+
+    // tampered s, tampered digest, and a public key off the curve
+    let other = Sha256::digest(b"other");
+    entries[3].2.sig += FpElem::new(1, n);
+    entries[11].1 = other.as_slice();
+    entries[20].0 = ECPoint::new(1u64, 1u64, 0u64, 5u64, 1000003u64);
+    assert_eq!(
+        verifier.verify_batch(&entries, &mut rng),
+        Err(BatchVerifyError {
+            invalid: vec![
+                (3, VerifyError::Mismatch),
+                (11, VerifyError::Mismatch),
+                (20, VerifyError::PubKeyNotOnCurve),
+            ]
+        })
+    );
+}
+
+#[test]
+fn verify_batch_strict() {
+    // the same signatures, high or low s, pass or fail as they do in verify_prehash
+    let domain = medium_curve();
+    let mut rng = StdRng::seed_from_u64(9);
+    let digest = Sha256::digest(b"batch");
+    let hash = digest_to_scalar(&digest, domain.n());
+    let keychain = KeyChain::generate(domain, &mut rng).unwrap();
+    let mut entries = Vec::new();
+    for _ in 0..10 {
+        let sig = keychain
+            .sign_recoverable_with_rng(&hash, &mut rng)
+            .unwrap()
+            .normalize_s();
+        entries.push((
+            keychain.pubkey,
+            digest.as_slice(),
+            sig.signature,
+            Some(sig.recovery_id),
+        ));
+    }
+    let high = Signature::new(entries[4].2.targetx, -entries[4].2.sig);
+    entries[4].2 = high;
+    entries[4].3 = entries[4].3.map(|id| id ^ 1);
+    assert!(!high.is_low_s());
+    assert_eq!(keychain.verify_batch(&entries, &mut rng), Ok(()));
+    let strict = keychain.with_strict_verification(true);
+    assert_eq!(
+        strict.verify_prehash(&digest, &high),
+        Err(VerifyError::HighS)
+    );
+    assert_eq!(
+        strict.verify_batch(&entries, &mut rng),
+        Err(BatchVerifyError {
+            invalid: vec![(4, VerifyError::HighS)]
+        })
+    );
+}
+
+#[test]
+fn verify_batch_beats_single_secp256k1() {
+    // the point of batching - one combined check of 32 signatures under different keys against
+    // verifying each one
+    let domain = CurveDomain::<U512>::secp256k1();
+    let n = domain.n();
+    let mut rng = StdRng::seed_from_u64(10);
+    let digests: Vec<_> = (0..32u8).map(|i| Sha256::digest([i]).to_vec()).collect();
+    let mut entries = Vec::new();
+    for digest in digests.iter() {
+        let keychain = KeyChain::generate(domain.clone(), &mut rng).unwrap();
+        let hash = digest_to_scalar(digest, n);
+        let sig = keychain.sign_recoverable_with_rng(&hash, &mut rng).unwrap();
+        entries.push((
+            keychain.pubkey,
+            digest.as_slice(),
+            sig.signature,
+            Some(sig.recovery_id),
+        ));
+    }
+    let verifier = KeyChain::new_pub(entries[0].0, domain.clone());
+    let start = Instant::now();
+    assert_eq!(verifier.verify_batch(&entries, &mut rng), Ok(()));
+    let batched = start.elapsed();
+    let start = Instant::now();
+    for (pubkey, digest, sig, _) in entries.iter() {
+        let keychain = KeyChain::new_pub(*pubkey, domain.clone());
+        assert_eq!(keychain.verify_prehash(digest, sig), Ok(()));
+    }
+    let singly = start.elapsed();
+    assert!(
+        batched < singly,
+        "batched {:?}, singly {:?}",
+        batched,
+        singly
+    );
+}
+
+#[test]
+fn rfc6979_nonces_other_hashes() {
+    // RFC 6979 A.2.5, P-256 signing "sample" with the other SHA-2 sizes
+    let n = CurveDomain::<U512>::p256().n();
+    let x = U512::from("0xc9afa9d845ba75166b5c215767b1d6934e50c3db36e89b127b8a622b120f6721");
+    let k224 = U512::from("0x103f90ee9dc52e5e7fb5132b7033c63066d194321491862059967c715985d473");
+    let k384 = U512::from("0x09f634b188cefd98e7ec88b1aa9852d734d0bc272f7d2a47decc6ebeb375aad4");
+    let k512 = U512::from("0x5fa81c63109badb88c1f367b47da606da28cad69aa22c4fe6ad7df73a7173aa5");
+    let z = |alg: HashAlgorithm| digest_to_scalar(&alg.digest(b"sample"), n).number;
+    let mut nonces = Rfc6979::<Sha224, U512>::new(x, z(HashAlgorithm::Sha224), n);
+    assert_eq!(nonces.next_k(), k224);
+    let mut nonces = Rfc6979::<Sha384, U512>::new(x, z(HashAlgorithm::Sha384), n);
+    assert_eq!(nonces.next_k(), k384);
+    let mut nonces = Rfc6979::<Sha512, U512>::new(x, z(HashAlgorithm::Sha512), n);
+    assert_eq!(nonces.next_k(), k512);
+    // straight from the digest, which is wider than n here
+    let digest = HashAlgorithm::Sha512.digest(b"sample");
+    let mut nonces = Rfc6979::<Sha512, U512>::from_digest(x, &digest, n);
+    assert_eq!(nonces.next_k(), k512);
+}
+
+#[test]
+fn sign_message_p256_sha512() {
+    // RFC 6979 A.2.5 again, the digest is wider than n so this exercises the truncation
+    let domain = CurveDomain::p256();
+    let n = domain.n();
+    let x = U512::from("0xc9afa9d845ba75166b5c215767b1d6934e50c3db36e89b127b8a622b120f6721");
+    let keychain = KeyChain::new_priv(FpElem::new(x, n), domain)
+        .with_nonce_mode(NonceMode::Rfc6979)
+        .with_hash_algorithm(HashAlgorithm::Sha512);
+    let sig = keychain.sign_message(b"sample").unwrap();
+    let r = U512::from("0x8496a60b5e9b47c825488827e0495b0e3fa109ec4568fd3f8d1097678eb97f00");
+    let s = U512::from("0x2362ab1adbe2b8adf9cb9edab740ea6049c028114f2460f96554f61fae3302fe");
+    assert_eq!((sig.targetx.number, sig.sig.number), (r, s));
+    assert!(keychain.verify_message(b"sample", &sig).is_ok());
+}
+
+#[test]
+fn sign_message_medium_curve() {
+    let domain = medium_curve();
+    let mut rng = StdRng::seed_from_u64(9);
+    for alg in [
+        HashAlgorithm::Sha224,
+        HashAlgorithm::Sha256,
+        HashAlgorithm::Sha384,
+        HashAlgorithm::Sha512,
+    ] {
+        let keychain = KeyChain::generate(domain.clone(), &mut rng)
+            .unwrap()
+            .with_hash_algorithm(alg);
+        let sig = keychain.sign_message_with_rng(b"hello", &mut rng).unwrap();
+        assert!(keychain.verify_message(b"hello", &sig).is_ok());
+        assert!(keychain.verify_prehash(&alg.digest(b"hello"), &sig).is_ok());
+        assert_eq!(
+            keychain.verify_message(b"hellp", &sig),
+            Err(VerifyError::Mismatch)
+        );
+
+        // the prehash path is the message path minus the hashing
+        let deterministic = keychain.with_nonce_mode(NonceMode::Rfc6979);
+        assert_eq!(
+            deterministic.sign_message(b"hello").unwrap(),
+            deterministic.sign_prehash(&alg.digest(b"hello")).unwrap()
+        );
+    }
+}
@@ -0,0 +1,108 @@
+use rustecdsg::{generic_uint_to_be_bytes, CurveDomain, CurveError, ECPoint, FpElem};
+extern crate primitive_types;
+use primitive_types::{U256, U512};
+
+// P-521 doesn't fit in U512
+#[allow(clippy::all)]
+mod u1024 {
+    uint::construct_uint! {
+        pub struct U1024(16);
+    }
+}
+use u1024::U1024;
+
+fn all_curves() -> Vec<CurveDomain<U1024>> {
+    vec![
+        CurveDomain::secp256k1(),
+        CurveDomain::secp224k1(),
+        CurveDomain::p224(),
+        CurveDomain::p256(),
+        CurveDomain::p384(),
+        CurveDomain::p521(),
+        CurveDomain::brainpool_p160r1(),
+        CurveDomain::brainpool_p192r1(),
+        CurveDomain::brainpool_p224r1(),
+        CurveDomain::brainpool_p256r1(),
+        CurveDomain::brainpool_p320r1(),
+        CurveDomain::brainpool_p384r1(),
+        CurveDomain::brainpool_p512r1(),
+    ]
+}
+
+#[test]
+fn named_curves_generator_on_curve() {
+    for domain in all_curves() {
+        let g = domain.generator();
+        assert!(g.is_on_curve(), "{}", domain.name);
+        assert_eq!(domain.cofactor, U1024::from(1));
+        assert_eq!(CurveDomain::from_name(domain.name), Some(domain));
+    }
+}
+
+#[test]
+fn named_curves_generator_order() {
+    // n * G = O, on the smaller curves as this is slow - the others get it from signing in cryptotests
+    for domain in [
+        CurveDomain::<U512>::brainpool_p160r1(),
+        CurveDomain::<U512>::secp224k1(),
+    ] {
+        assert_eq!((domain.generator() * domain.n()).position, None);
+        assert!(domain
+            .generator()
+            .is_in_subgroup(domain.n(), domain.cofactor));
+    }
+}
+
+#[test]
+fn curve_domain_from_name() {
+    let p256 = CurveDomain::<U512>::p256();
+    for name in ["P-256", "secp256r1", "prime256v1"] {
+        assert_eq!(CurveDomain::from_name(name), Some(p256.clone()));
+    }
+    assert_eq!(
+        CurveDomain::from_name("secp384r1"),
+        Some(CurveDomain::<U512>::p384())
+    );
+    assert_eq!(CurveDomain::<U512>::from_name("P-255"), None);
+    assert_eq!(
+        p256.n(),
+        U512::from("0xffffffff00000000ffffffffffffffffbce6faada7179e84f3b9cac2fc632551")
+    );
+}
+
+#[test]
+fn curve_domain_new() {
+    let g: ECPoint<FpElem<u64>> = ECPoint::new(5u64, 1u64, 2u64, 2u64, 17u64);
+    let domain = CurveDomain::new("toy", g, 19, 1).unwrap();
+    assert_eq!((domain.p, domain.a, domain.b), (17, 2, 2));
+    assert_eq!(domain.generator(), g);
+
+    let off = ECPoint::new(5u64, 2u64, 2u64, 2u64, 17u64);
+    assert_eq!(
+        CurveDomain::new("toy", off, 19u64, 1),
+        Err(CurveError::NotOnCurve)
+    );
+    let inf = ECPoint::new_infinity(2u64, 2u64, 17u64);
+    assert_eq!(
+        CurveDomain::new("toy", inf, 19u64, 1),
+        Err(CurveError::GeneratorAtInfinity)
+    );
+}
+
+#[test]
+#[should_panic(expected = "secp256k1 does not fit")]
+fn named_curve_too_narrow() {
+    CurveDomain::<u64>::secp256k1();
+}
+
+#[test]
+fn named_curve_full_width() {
+    // p and n can take up all of T, there's no need for a wider type
+    let narrow = CurveDomain::<U256>::secp256k1();
+    let wide = CurveDomain::<U512>::secp256k1();
+    let widen = |x: U256| U512::from_big_endian(&generic_uint_to_be_bytes(x, 32));
+    assert_eq!((widen(narrow.p), widen(narrow.n())), (wide.p, wide.n()));
+    assert!(narrow.generator().is_on_curve());
+    let brainpool = CurveDomain::<U512>::brainpool_p512r1();
+    assert!(brainpool.generator().is_on_curve());
+}
@@ -0,0 +1,212 @@
+use rustecdsg::{ArithmeticError, CurveError, ECPoint, FpElem};
+extern crate primitive_types;
+use primitive_types::U512;
+
+#[test]
+fn ecpoint_is_on_curve() {
+    let p = U512::from("0xfffffffffffffffffffffffffffffffffffffffffffffffffffffffefffffc2f");
+    let gx = U512::from("0x79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798");
+    let gy = U512::from("0x483ada7726a3c4655da4fbfc0e1108a8fd17b448a68554199c47d08ffb10d4b8");
+    let g: ECPoint<FpElem<U512>> = ECPoint::new(gx, gy, 0, 7, p);
+    assert!(g.is_on_curve());
+    assert!(!ECPoint::<FpElem<U512>>::new(gx, gy + U512::from(1), 0, 7, p).is_on_curve());
+    assert!(ECPoint::<FpElem<U512>>::new_infinity(0, 7, p).is_on_curve());
+
+    // format: ((x, y), on y^2 = x^3 + 2x + 2 over F_17)
+    let points = [
+        ((5, 1), true),
+        ((6, 3), true),
+        ((0, 6), true),
+        ((5, 2), false),
+        ((1, 1), false),
+    ];
+    for ((x, y), expected) in points.iter() {
+        let point: ECPoint<FpElem<u64>> = ECPoint::new(*x as u64, *y as u64, 2u64, 2u64, 17u64);
+        assert_eq!(point.is_on_curve(), *expected);
+    }
+}
+
+#[test]
+fn ecpoint_try_new() {
+    let point = ECPoint::<FpElem<u64>>::try_new(5u64, 1u64, 2u64, 2u64, 17u64);
+    assert_eq!(point, Ok(ECPoint::new(5u64, 1u64, 2u64, 2u64, 17u64)));
+    let point = ECPoint::<FpElem<u64>>::try_new(5u64, 2u64, 2u64, 2u64, 17u64);
+    assert_eq!(point, Err(CurveError::NotOnCurve));
+    // (22, 1) would be (5, 1) again once reduced
+    let point = ECPoint::<FpElem<u64>>::try_new(22u64, 1u64, 2u64, 2u64, 17u64);
+    assert_eq!(point, Err(CurveError::CoordinateOutOfRange));
+    let point = ECPoint::<FpElem<u64>>::try_new(5u64, 18u64, 2u64, 2u64, 17u64);
+    assert_eq!(point, Err(CurveError::CoordinateOutOfRange));
+}
+
+#[test]
+fn ecpoint_from_x() {
+    let point = ECPoint::<FpElem<u64>>::from_x(5u64, true, 2u64, 2u64, 17u64);
+    assert_eq!(point, Some(ECPoint::new(5u64, 1u64, 2u64, 2u64, 17u64)));
+    let point = ECPoint::<FpElem<u64>>::from_x(5u64, false, 2u64, 2u64, 17u64);
+    assert_eq!(point, Some(ECPoint::new(5u64, 16u64, 2u64, 2u64, 17u64)));
+    let point = ECPoint::<FpElem<u64>>::from_x(22u64, true, 2u64, 2u64, 17u64);
+    assert_eq!(point, None);
+    // (0, 0) on y^2 = x^3 - x has no odd y
+    let point = ECPoint::<FpElem<u64>>::from_x(0u64, false, 16u64, 0u64, 17u64);
+    assert_eq!(point, Some(ECPoint::new(0u64, 0u64, 16u64, 0u64, 17u64)));
+    let point = ECPoint::<FpElem<u64>>::from_x(0u64, true, 16u64, 0u64, 17u64);
+    assert_eq!(point, None);
+}
+
+#[test]
+fn ecpoint_is_in_subgroup() {
+    // y^2 = x^3 + 7 over F_223 has 252 = 21 * 12 points
+    let curve = |x: u64, y: u64| -> ECPoint<FpElem<u64>> { ECPoint::new(x, y, 0u64, 7u64, 223u64) };
+    // (47, 71) has order 21, (6, 0) order 2 and (0, 26) order 3
+    assert!(curve(47, 71).is_in_subgroup(21, 12));
+    assert!((curve(47, 71) * 5).is_in_subgroup(21, 12));
+    assert!(!curve(6, 0).is_in_subgroup(21, 12));
+    assert!(!curve(0, 26).is_in_subgroup(7, 36));
+    assert!(curve(0, 26).is_in_subgroup(3, 84));
+    // off the curve is never in the subgroup
+    assert!(!curve(47, 70).is_in_subgroup(21, 12));
+    assert!(ECPoint::<FpElem<u64>>::new_infinity(0u64, 7u64, 223u64).is_in_subgroup(21, 12));
+
+    // with cofactor 1 every curve point is in the group, no multiplication needed
+    let g: ECPoint<FpElem<u64>> = ECPoint::new(5u64, 1u64, 2u64, 2u64, 17u64);
+    assert!(g.is_in_subgroup(19, 1));
+}
+
+#[test]
+fn ecpoint_mul_secret_matches_mul() {
+    // every scalar mod 19 on the toy curve, and mod 21 for (47, 71) on the F_223 one, which has a
+    // point of order 2 elsewhere on the curve. (3, 6) on y^2 = x^3 + 3x over F_17 has order 26
+    // itself, so the ladder falls back to Jacobian there
+    let g: ECPoint<FpElem<u64>> = ECPoint::new(5u64, 1u64, 2u64, 2u64, 17u64);
+    let h: ECPoint<FpElem<u64>> = ECPoint::new(47u64, 71u64, 0u64, 7u64, 223u64);
+    let e: ECPoint<FpElem<u64>> = ECPoint::new(3u64, 6u64, 3u64, 0u64, 17u64);
+    for (point, order) in [(g, 19u64), (h, 21), (e, 26), (e * 13, 2)] {
+        for k in 0..order {
+            assert_eq!(point.mul_secret(&FpElem::new(k, order)), point * k);
+        }
+    }
+
+    let p = U512::from("0xfffffffffffffffffffffffffffffffffffffffffffffffffffffffefffffc2f");
+    let n = U512::from("0xfffffffffffffffffffffffffffffffebaaedce6af48a03bbfd25e8cd0364141");
+    let gx = U512::from("0x79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798");
+    let gy = U512::from("0x483ada7726a3c4655da4fbfc0e1108a8fd17b448a68554199c47d08ffb10d4b8");
+    let g: ECPoint<FpElem<U512>> = ECPoint::new(gx, gy, 0, 7, p);
+    for k in [
+        U512::from(1),
+        U512::from(2),
+        U512::from("0xaa5e28d6a97a2479a65527f7290311a3624d4cc0fa1578598ee3c2613bf99522"),
+        n - U512::from(1),
+    ] {
+        assert_eq!(g.mul_secret(&FpElem::new(k, n)), g * k);
+    }
+    assert_eq!(g.mul_secret(&FpElem::new(U512::from(0), n)).position, None);
+}
+
+#[test]
+fn ecpoint_neg() {
+    let g: ECPoint<FpElem<u64>> = ECPoint::new(5u64, 1u64, 2u64, 2u64, 17u64);
+    assert_eq!(-g, ECPoint::new(5u64, 16u64, 2u64, 2u64, 17u64));
+    assert_eq!((g + -g).position, None);
+    assert_eq!(-(-g), g);
+    let inf = ECPoint::<FpElem<u64>>::new_infinity(2u64, 2u64, 17u64);
+    assert_eq!(-inf, inf);
+    // (6, 0) has order 2 so is its own negative
+    let t: ECPoint<FpElem<u64>> = ECPoint::new(6u64, 0u64, 0u64, 7u64, 223u64);
+    assert_eq!(-t, t);
+}
+
+#[test]
+fn ecpoint_mul_wnaf() {
+    // small scalars against repeated addition, so windows 2 and 3 both get used
+    let g: ECPoint<FpElem<u64>> = ECPoint::new(5u64, 1u64, 2u64, 2u64, 17u64);
+    let mut expected = ECPoint::new_infinity(2u64, 2u64, 17u64);
+    for k in 0..100u64 {
+        assert_eq!(g * k, expected, "{}", k);
+        expected += g;
+    }
+    // as big as the type goes, g has order 19
+    assert_eq!(g * u64::MAX, g * (u64::MAX % 19));
+    assert_eq!(g * (u64::MAX - 1), g * ((u64::MAX - 1) % 19));
+
+    // scalars of all sizes on secp256k1 against the ladder, reducing mod n where they're too big
+    // for it - n G = O so k G = (k mod n) G
+    let p = U512::from("0xfffffffffffffffffffffffffffffffffffffffffffffffffffffffefffffc2f");
+    let n = U512::from("0xfffffffffffffffffffffffffffffffebaaedce6af48a03bbfd25e8cd0364141");
+    let gx = U512::from("0x79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798");
+    let gy = U512::from("0x483ada7726a3c4655da4fbfc0e1108a8fd17b448a68554199c47d08ffb10d4b8");
+    let g: ECPoint<FpElem<U512>> = ECPoint::new(gx, gy, 0, 7, p);
+    for k in [
+        "0x1f",
+        "0xdeadbeefcafe",
+        "0x9e3779b97f4a7c15f39cc0605cedc834",
+        "0xaa5e28d6a97a2479a65527f7290311a3624d4cc0fa1578598ee3c2613bf99522",
+        "0xffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff",
+        "0x5555555555555555555555555555555555555555555555555555555555555555\
+         5555555555555555555555555555555555555555555555555555555555555555",
+    ] {
+        let k = U512::from(k);
+        assert_eq!(g * k, g.mul_secret(&FpElem::new(k, n)));
+    }
+}
+
+#[test]
+// every owned and borrowed combination, the borrowed ones on purpose
+#[allow(clippy::op_ref)]
+fn ecpoint_operator_forms() {
+    let g: ECPoint<FpElem<u64>> = ECPoint::new(5u64, 1u64, 2u64, 2u64, 17u64);
+    let (p, q) = (g * 3, g * 5);
+    assert_eq!(&p + &q, g * 8);
+    assert_eq!(p + &q, g * 8);
+    assert_eq!(&p + q, g * 8);
+    assert_eq!(q - p, g * 2);
+    assert_eq!(&q - &p, g * 2);
+    assert_eq!(q - &p, g * 2);
+    assert_eq!(&p - q, g * 17);
+    assert_eq!(-&p, g * 16);
+    assert_eq!((p - p).position, None);
+    assert_eq!(&g * 4u64, g * 4);
+
+    let mut r = p;
+    r += q;
+    r -= &p;
+    assert_eq!(r, q);
+    r += &p;
+    r -= q;
+    assert_eq!(r, p);
+    r *= 6;
+    assert_eq!(r, g * 18);
+
+    // 1 + 2 + ... + 6 = 21 = 2 mod 19
+    let points: Vec<ECPoint<FpElem<u64>>> = (1..7u64).map(|k| g * k).collect();
+    assert_eq!(ECPoint::try_sum(points.iter().copied()), Some(g * 2));
+    // Sum gives an Option too, None for no points
+    assert_eq!(points.iter().sum::<Option<_>>(), Some(g * 2));
+    assert_eq!(Vec::<ECPoint<FpElem<u64>>>::new().iter().sum::<Option<_>>(), None);
+    assert_eq!(ECPoint::try_sum(points.clone()), Some(g * 2));
+    assert_eq!(points.into_iter().sum::<Option<_>>(), Some(g * 2));
+    assert_eq!(ECPoint::try_sum([g, -g]).unwrap().position, None);
+    assert_eq!(ECPoint::<FpElem<u64>>::try_sum([]), None);
+}
+
+#[test]
+fn ecpoint_checked() {
+    let g: ECPoint<FpElem<u64>> = ECPoint::new(5u64, 1u64, 2u64, 2u64, 17u64);
+    let (p, q) = (g * 3, g * 5);
+    assert_eq!(p.checked_add(&q), Ok(g * 8));
+    assert_eq!(q.checked_sub(&p), Ok(g * 2));
+    assert_eq!(p.checked_sub(&p), Ok(g * 19));
+
+    let other: ECPoint<FpElem<u64>> = ECPoint::new(5u64, 1u64, 2u64, 3u64, 17u64);
+    assert_eq!(g.checked_add(&other), Err(ArithmeticError::CurveMismatch));
+    assert_eq!(g.checked_sub(&other), Err(ArithmeticError::CurveMismatch));
+    let elsewhere = ECPoint::new_infinity(2u64, 2u64, 19u64);
+    assert_eq!(g.checked_add(&elsewhere), Err(ArithmeticError::CurveMismatch));
+
+    // mod 15 the chord from x = 1 to x = 6 has slope over 5, which doesn't invert
+    let (r, s): (ECPoint<FpElem<u64>>, _) = (
+        ECPoint::new(1u64, 1u64, 0u64, 0u64, 15u64),
+        ECPoint::new(6u64, 2u64, 0u64, 0u64, 15u64),
+    );
+    assert_eq!(r.checked_add(&s), Err(ArithmeticError::NotInvertible));
+}
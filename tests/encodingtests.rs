@@ -0,0 +1,219 @@
+use rustecdsg::{ECPoint, EncodingError, FpElem, Signature};
+extern crate primitive_types;
+use primitive_types::U512;
+
+fn hex(s: &str) -> Vec<u8> {
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+        .collect()
+}
+
+fn secp256k1_order() -> U512 {
+    U512::from("0xfffffffffffffffffffffffffffffffebaaedce6af48a03bbfd25e8cd0364141")
+}
+
+fn example_sig() -> Signature<FpElem<U512>> {
+    let n = secp256k1_order();
+    let r = U512::from("0x37206a0610995c58074999cb9767b87af4c4978db68c06e8e6e81d282047a7c6");
+    let s = U512::from("0x8ca63759c1157ebeaec0d03cecca119fc9a75bf8e6d0fa65c841c8e2738cdaec");
+    Signature::new(FpElem::new(r, n), FpElem::new(s, n))
+}
+
+#[test]
+fn signature_der_roundtrip() {
+    let n = secp256k1_order();
+    let sig = example_sig();
+    // s has its top bit set so picks up a leading zero, r doesn't
+    let der = hex(concat!(
+        "3045",
+        "022037206a0610995c58074999cb9767b87af4c4978db68c06e8e6e81d282047a7c6",
+        "0221008ca63759c1157ebeaec0d03cecca119fc9a75bf8e6d0fa65c841c8e2738cdaec"
+    ));
+    assert_eq!(sig.to_der(), der);
+    assert_eq!(Signature::from_der(&der, n), Ok(sig));
+
+    // small values shrink right down
+    let small = Signature::new(
+        FpElem::new(U512::from(1), n),
+        FpElem::new(U512::from(0x80), n),
+    );
+    let der = hex("3007020101020200 80".replace(' ', "").as_str());
+    assert_eq!(small.to_der(), der);
+    assert_eq!(Signature::from_der(&der, n), Ok(small));
+
+    // toy sized orders work the same way
+    let toy = Signature::new(FpElem::new(3u64, 19), FpElem::new(18u64, 19));
+    assert_eq!(Signature::from_der(&toy.to_der(), 19), Ok(toy));
+}
+
+#[test]
+fn signature_der_strict() {
+    let n = U512::from(0xffffu64);
+    // format: (hex, expected error)
+    let bad = [
+        ("", EncodingError::Truncated),
+        (
+            "3106020101020101",
+            EncodingError::UnexpectedTag {
+                expected: 0x30,
+                found: 0x31,
+            },
+        ),
+        (
+            "3006030101020101",
+            EncodingError::UnexpectedTag {
+                expected: 0x02,
+                found: 0x03,
+            },
+        ),
+        ("3007020101020101", EncodingError::Truncated),
+        ("300602010102010100", EncodingError::TrailingBytes(1)),
+        ("3007020101020101 00", EncodingError::TrailingBytes(1)),
+        // long form length for something short
+        ("308106020101020101", EncodingError::NonMinimalLength),
+        ("3080020101020101 0000", EncodingError::NonMinimalLength),
+        // padding that isn't needed
+        ("300702020001020101", EncodingError::NonMinimalInteger),
+        ("3006020181020101", EncodingError::NegativeInteger),
+        ("30050200020101", EncodingError::Truncated),
+        // r = n, and r wider than n could ever be
+        ("3008020300ffff020101", EncodingError::IntegerOutOfRange),
+        ("3008020301000002 0101", EncodingError::IntegerOutOfRange),
+    ];
+    for (der, err) in bad.iter() {
+        let der = hex(&der.replace(' ', ""));
+        assert_eq!(Signature::from_der(&der, n), Err(err.clone()), "{:?}", der);
+    }
+    // zero is a perfectly good DER integer, verify_sig is what turns it down
+    let zero = hex("3006020100020101");
+    assert!(Signature::from_der(&zero, n).is_ok());
+}
+
+#[test]
+fn signature_fixed_width() {
+    let n = secp256k1_order();
+    let sig = example_sig();
+    let bytes = sig.to_bytes();
+    assert_eq!(bytes.len(), 64);
+    assert_eq!(
+        bytes,
+        hex(concat!(
+            "37206a0610995c58074999cb9767b87af4c4978db68c06e8e6e81d282047a7c6",
+            "8ca63759c1157ebeaec0d03cecca119fc9a75bf8e6d0fa65c841c8e2738cdaec"
+        ))
+    );
+    assert_eq!(Signature::from_bytes(&bytes, n), Ok(sig));
+
+    // r and s are padded out to the full width
+    let small = Signature::new(FpElem::new(U512::from(1), n), FpElem::new(U512::from(2), n));
+    let bytes = small.to_bytes();
+    assert_eq!((bytes[31], bytes[63]), (1, 2));
+    assert!(bytes.iter().filter(|b| **b != 0).count() == 2);
+    assert_eq!(Signature::from_bytes(&bytes, n), Ok(small));
+
+    assert_eq!(
+        Signature::from_bytes(&bytes[1..], n),
+        Err(EncodingError::WrongLength {
+            expected: 64,
+            found: 63
+        })
+    );
+    let mut too_big = bytes.clone();
+    too_big[..32].copy_from_slice(&[0xff; 32]);
+    assert_eq!(
+        Signature::from_bytes(&too_big, n),
+        Err(EncodingError::IntegerOutOfRange)
+    );
+}
+
+#[test]
+fn sec1_secp256k1_generator() {
+    let p = U512::from("0xfffffffffffffffffffffffffffffffffffffffffffffffffffffffefffffc2f");
+    let gx = "79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798";
+    let gy = "483ada7726a3c4655da4fbfc0e1108a8fd17b448a68554199c47d08ffb10d4b8";
+    let g: ECPoint<FpElem<U512>> = ECPoint::new(
+        U512::from_str_radix(gx, 16).unwrap(),
+        U512::from_str_radix(gy, 16).unwrap(),
+        0,
+        7,
+        p,
+    );
+    // y ends in 8 so is even
+    let compressed = hex(&format!("02{}", gx));
+    let uncompressed = hex(&format!("04{}{}", gx, gy));
+    assert_eq!(g.to_sec1(true), compressed);
+    assert_eq!(g.to_sec1(false), uncompressed);
+    assert_eq!(ECPoint::from_sec1(&compressed, 0, 7, p), Ok(g));
+    assert_eq!(ECPoint::from_sec1(&uncompressed, 0, 7, p), Ok(g));
+
+    let inf: ECPoint<FpElem<U512>> = ECPoint::new_infinity(0, 7, p);
+    assert_eq!(inf.to_sec1(true), vec![0x00]);
+    assert_eq!(inf.to_sec1(false), vec![0x00]);
+    assert_eq!(ECPoint::from_sec1(&[0x00], 0, 7, p), Ok(inf));
+}
+
+#[test]
+fn sec1_roundtrip_toy_curve() {
+    // every point of y^2 = x^3 + 2x + 2 over F_17
+    let g: ECPoint<FpElem<u64>> = ECPoint::new(5u64, 1u64, 2u64, 2u64, 17u64);
+    let mut point = g;
+    for _ in 0..19 {
+        for compressed in [true, false] {
+            let bytes = point.to_sec1(compressed);
+            assert_eq!(ECPoint::from_sec1(&bytes, 2u64, 2u64, 17u64), Ok(point));
+        }
+        point += g;
+    }
+}
+
+#[test]
+fn sec1_rejects_bad_points() {
+    let decode = |bytes: &[u8]| ECPoint::<FpElem<u64>>::from_sec1(bytes, 2u64, 2u64, 17u64);
+    // format: (bytes, expected error)
+    let bad: [(&[u8], EncodingError); 9] = [
+        (&[], EncodingError::Truncated),
+        (&[0x05, 0x01], EncodingError::InvalidPointTag(0x05)),
+        // hybrid encodings aren't supported
+        (&[0x06, 0x05, 0x01], EncodingError::InvalidPointTag(0x06)),
+        (
+            &[0x02],
+            EncodingError::WrongLength {
+                expected: 2,
+                found: 1,
+            },
+        ),
+        (
+            &[0x04, 0x05],
+            EncodingError::WrongLength {
+                expected: 3,
+                found: 2,
+            },
+        ),
+        (
+            &[0x00, 0x00],
+            EncodingError::WrongLength {
+                expected: 1,
+                found: 2,
+            },
+        ),
+        (&[0x02, 0x11], EncodingError::CoordinateOutOfRange),
+        // (5, 2) is off the curve, and nothing has x = 4 (4^3 + 8 + 2 = 6 isn't a square mod 17)
+        (&[0x04, 0x05, 0x02], EncodingError::NotOnCurve),
+        (&[0x02, 0x04], EncodingError::NotOnCurve),
+    ];
+    for (bytes, err) in bad.iter() {
+        assert_eq!(decode(bytes), Err(err.clone()), "{:?}", bytes);
+    }
+
+    // y^2 = x^3 - x over F_17 has (0, 0), and -(0, 0) = (0, 0) so only 0x02 is canonical
+    let zero = ECPoint::<FpElem<u64>>::new(0u64, 0u64, 16u64, 0u64, 17u64);
+    assert_eq!(
+        ECPoint::from_sec1(&[0x02, 0x00], 16u64, 0u64, 17u64),
+        Ok(zero)
+    );
+    assert_eq!(
+        ECPoint::<FpElem<u64>>::from_sec1(&[0x03, 0x00], 16u64, 0u64, 17u64),
+        Err(EncodingError::NotOnCurve)
+    );
+}
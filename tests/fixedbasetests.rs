@@ -0,0 +1,72 @@
+use rustecdsg::{CurveDomain, ECPoint, FixedBaseTable, FpElem, KeyChain};
+extern crate primitive_types;
+use primitive_types::U512;
+use std::sync::Arc;
+
+#[test]
+fn fixed_base_small_curves() {
+    // every scalar, on the toy curve and for (47, 71) of order 21 on y^2 = x^3 + 7 over F_223,
+    // and (3, 6) of even order 26 on y^2 = x^3 + 3x over F_17
+    let g: ECPoint<FpElem<u64>> = ECPoint::new(5u64, 1u64, 2u64, 2u64, 17u64);
+    let h: ECPoint<FpElem<u64>> = ECPoint::new(47u64, 71u64, 0u64, 7u64, 223u64);
+    let e: ECPoint<FpElem<u64>> = ECPoint::new(3u64, 6u64, 3u64, 0u64, 17u64);
+    for (point, order) in [(g, 19u64), (h, 21), (e, 26)] {
+        let table = FixedBaseTable::new(&point, order);
+        assert_eq!(*table.base(), point);
+        for k in 0..order {
+            assert_eq!(table.mul_secret(&FpElem::new(k, order)), point * k);
+        }
+    }
+}
+
+#[test]
+fn fixed_base_named_curves() {
+    for domain in [
+        CurveDomain::<U512>::secp256k1(),
+        CurveDomain::<U512>::p256(),
+    ] {
+        let (g, n) = (domain.generator(), domain.n());
+        let table = FixedBaseTable::new(&g, n);
+        for k in [
+            U512::from(0),
+            U512::from(1),
+            U512::from(0x10),
+            U512::from("0xaa5e28d6a97a2479a65527f7290311a3624d4cc0fa1578598ee3c2613bf99522"),
+            n - U512::from(1),
+        ] {
+            assert_eq!(table.mul_secret(&FpElem::new(k, n)), g * k);
+        }
+    }
+}
+
+#[test]
+#[should_panic(expected = "scalar must be mod the table's order")]
+fn fixed_base_wrong_modulus() {
+    let g: ECPoint<FpElem<u64>> = ECPoint::new(5u64, 1u64, 2u64, 2u64, 17u64);
+    FixedBaseTable::new(&g, 19u64).mul_secret(&FpElem::new(3, 17));
+}
+
+#[test]
+fn domain_generator_table() {
+    let domain = CurveDomain::<U512>::secp256k1();
+    let table = domain.generator_table();
+    assert_eq!(*table.base(), domain.generator());
+    // built once and shared with every clone, which is what KeyChain holds
+    assert!(Arc::ptr_eq(&domain.clone().generator_table(), &table));
+    // a clone taken before the first build shares the one build too, whichever asks first
+    let fresh = CurveDomain::<U512>::secp256k1();
+    let early = fresh.clone();
+    let built = early.generator_table();
+    assert!(Arc::ptr_eq(&fresh.generator_table(), &built));
+    assert!(!Arc::ptr_eq(&built, &table));
+
+    let privkey = FpElem::new(U512::from(123456789), domain.n());
+    let keychain = KeyChain::new_priv(privkey, domain.clone());
+    assert_eq!(keychain.pubkey, domain.generator() * U512::from(123456789));
+    assert!(Arc::ptr_eq(&keychain.domain().generator_table(), &table));
+    let hash = FpElem::new(U512::from(42), domain.n());
+    let sig = keychain.sign(&hash).unwrap();
+    assert!(KeyChain::new_pub(keychain.pubkey, domain)
+        .verify_sig(&hash, &sig)
+        .is_ok());
+}
@@ -1,9 +1,13 @@
-use rustecdsg::FpElem;
+// &a op &b is used throughout, and the borrowed forms are tested on purpose
+#![allow(clippy::op_ref)]
+use rustecdsg::{ArithmeticError, FpElem};
 use std::fmt::Debug;
 use rustecdsg::Pow;
 use rustecdsg::GenericUInt;
 extern crate primitive_types;
 use primitive_types::{U256, U512};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
 
 #[test]
 fn fpelem_new() {
@@ -63,6 +67,44 @@ fn fpelem_mul() {
     }
 }
 
+#[test]
+fn fpelem_mul_no_room_for_products() {
+    // products mod 2^61 - 1 don't fit in a u64, so this goes through the add based path
+    let m = (1u64 << 61) - 1;
+    // format: ((lhs, rhs), result)
+    let pairs = [
+        ((m - 1, m - 1), 1),
+        ((123456789123456789, 987654321987654321), 587437849037674763),
+        ((1 << 60, 3), 1152921504606846977),
+    ];
+    for ((lhs, rhs), out) in pairs.iter() {
+        assert_eq!(&FpElem::new(*lhs, m) * &FpElem::new(*rhs, m), FpElem::new(*out, m));
+    }
+
+    // and 2^255 - 19 in a U256 should agree with the same thing in a U512
+    let p = "0x7fffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffed";
+    let x = "0x216936d3cd6e53fec0a4e231fdd6dc5c692cc7609525a7b2c9562d608f25d51a";
+    let y = "0x6666666666666666666666666666666666666666666666666666666666666658";
+    let wide = &FpElem::new(U512::from(x), U512::from(p)) * &FpElem::new(U512::from(y), U512::from(p));
+    let narrow = &FpElem::new(U256::from(x), U256::from(p)) * &FpElem::new(U256::from(y), U256::from(p));
+    assert_eq!(U512::from(narrow.number), wide.number);
+}
+
+#[test]
+fn fpelem_u8() {
+    // every product mod 13 fits in a u8 and goes through the multiply and reduce path, mod 251
+    // they don't - both against the same thing worked out in a u32
+    for p in [7u8, 13, 251] {
+        for a in 0..p {
+            for b in 0..p {
+                let expected = (a as u32 * b as u32 % p as u32) as u8;
+                assert_eq!(&FpElem::new(a, p) * &FpElem::new(b, p), FpElem::new(expected, p));
+            }
+        }
+    }
+    assert_eq!(FpElem::new(3u8, 7u8).pow(6).number, 1);
+}
+
 #[test]
 fn fpelem_div() {
     // format: ((lhs, rhs, mod), result)
@@ -110,3 +152,173 @@ fn fpelem_pow(){
 }
 
 
+
+#[test]
+fn fpelem_random() {
+    let p = U256::from("0xfffffffffffffffffffffffffffffffffffffffffffffffffffffffefffffc2f");
+    let mut rng = StdRng::seed_from_u64(17);
+    for _ in 0..100 {
+        let x = FpElem::random(p, &mut rng);
+        assert!(x.number < p);
+        assert_eq!(x.prime, p);
+    }
+    // seeded rngs give reproducible elements
+    let a = FpElem::random(p, &mut StdRng::seed_from_u64(1));
+    let b = FpElem::random(p, &mut StdRng::seed_from_u64(1));
+    assert_eq!(a, b);
+
+    // small field, every element should turn up and 0 never should for random_nonzero
+    let mut seen = [false; 7];
+    for _ in 0..1000 {
+        let x = FpElem::random_nonzero(7u32, &mut rng);
+        assert_ne!(x.number, 0);
+        seen[x.number as usize] = true;
+    }
+    assert!(seen[1..].iter().all(|s| *s));
+}
+
+// every element of a few small fields, covering each sqrt branch:
+// 3 mod 4 (7, 23), 5 mod 8 (13, 29) and 1 mod 8 (17, 41, 97)
+#[test]
+fn fpelem_sqrt_small_primes() {
+    for p in [2u32, 3, 7, 23, 13, 29, 17, 41, 97] {
+        let mut squares = vec![false; p as usize];
+        for x in 0..p {
+            squares[(x * x % p) as usize] = true;
+        }
+        for x in 0..p {
+            let elem = FpElem::new(x, p);
+            let expected = if x == 0 {
+                0
+            } else if squares[x as usize] {
+                1
+            } else {
+                -1
+            };
+            assert_eq!(elem.legendre(), expected, "({}/{})", x, p);
+            assert_eq!(elem.is_square(), squares[x as usize]);
+            match elem.sqrt() {
+                Some(root) => assert_eq!(&root * &root, elem),
+                None => assert!(!squares[x as usize]),
+            }
+        }
+    }
+}
+
+#[test]
+fn fpelem_sqrt_large_primes() {
+    let primes = [
+        // secp256k1, 3 mod 4
+        "0xfffffffffffffffffffffffffffffffffffffffffffffffffffffffefffffc2f",
+        // P-256, 3 mod 4
+        "0xffffffff00000001000000000000000000000000ffffffffffffffffffffffff",
+        // 2^255 - 19, 5 mod 8
+        "0x7fffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffed",
+    ];
+    let mut rng = StdRng::seed_from_u64(12);
+    for p in primes.iter() {
+        let p = U256::from(*p);
+        for _ in 0..4 {
+            let x = FpElem::random_nonzero(p, &mut rng);
+            let square = &x * &x;
+            assert_eq!(square.legendre(), 1);
+            let root = square.sqrt().unwrap();
+            assert!(root == x || &root + &x == FpElem::new(U256::from(0), p));
+        }
+        // -1 is a square exactly when p = 1 mod 4
+        let minus_one = FpElem::new(p - U256::from(1), p);
+        let one_mod_four = p % U256::from(4) == U256::from(1);
+        assert_eq!(minus_one.is_square(), one_mod_four);
+        assert_eq!(minus_one.sqrt().is_some(), one_mod_four);
+        assert_eq!(
+            FpElem::new(U256::from(0), p).sqrt().unwrap().number,
+            U256::from(0)
+        );
+    }
+}
+
+#[test]
+fn fpelem_sqrt_tonelli_shanks() {
+    // 2^64 - 2^32 + 1 is 1 mod 2^32, so Tonelli-Shanks has a lot of work to do
+    let p = 0xffffffff00000001u128;
+    let mut rng = StdRng::seed_from_u64(32);
+    for _ in 0..8 {
+        let x = FpElem::random_nonzero(p, &mut rng);
+        let square = &x * &x;
+        let root = square.sqrt().unwrap();
+        assert!(root == x || (root.number + x.number) == p);
+        // 7 generates the multiplicative group so is a non square, and so is any square times it
+        assert_eq!((&square * 7).sqrt(), None);
+    }
+
+    // P-224 is 1 mod 2^96, one root is plenty
+    let p = U256::from("0xffffffffffffffffffffffffffffffff000000000000000000000001");
+    let x = FpElem::random_nonzero(p, &mut rng);
+    let root = (&x * &x).sqrt().unwrap();
+    assert!(root == x || &root + &x == FpElem::new(U256::from(0), p));
+}
+
+#[test]
+fn fpelem_operator_forms() {
+    let (a, b) = (FpElem::new(9192u64, 65537), FpElem::new(127712u64, 65537));
+    // every owned/borrowed combination agrees with &a op &b
+    assert_eq!(a + b, &a + &b);
+    assert_eq!(a + &b, &a + &b);
+    assert_eq!(&a + b, &a + &b);
+    assert_eq!(a - b, &a - &b);
+    assert_eq!(&a - b, &a - &b);
+    assert_eq!(a * &b, &a * &b);
+    assert_eq!(a / b, &a / &b);
+    assert_eq!(a * 3, &a * 3);
+
+    let mut c = a;
+    c += b;
+    c -= &b;
+    assert_eq!(c, a);
+    c *= b;
+    c /= &b;
+    assert_eq!(c, a);
+    c *= 2;
+    assert_eq!(c, a + a);
+
+    // format: (x, -x) mod 65537
+    let pairs = [(0, 0), (1, 65536), (9192, 56345)];
+    for (x, negx) in pairs.iter() {
+        let x = FpElem::new(*x as u64, 65537);
+        assert_eq!(-x, FpElem::new(*negx as u64, 65537));
+        assert_eq!(-&x + x, FpElem::new(0, 65537));
+    }
+
+    let elems = [a, b, FpElem::new(5, 65537)];
+    let expected = &(&a + &b) + &FpElem::new(5, 65537);
+    assert_eq!(FpElem::try_sum(elems.iter().copied()), Some(expected));
+    assert_eq!(FpElem::try_sum(elems), Some(expected));
+    assert_eq!(FpElem::<u64>::try_sum([]), None);
+    assert_eq!(elems.iter().sum::<Option<_>>(), Some(expected));
+    assert_eq!(elems.into_iter().sum::<Option<_>>(), Some(expected));
+    assert_eq!(Vec::<FpElem<u64>>::new().into_iter().sum::<Option<_>>(), None);
+}
+
+#[test]
+fn fpelem_checked() {
+    let (a, b) = (FpElem::new(9192u64, 65537), FpElem::new(127712u64, 65537));
+    assert_eq!(a.checked_add(&b), Ok(&a + &b));
+    assert_eq!(a.checked_sub(&b), Ok(&a - &b));
+    assert_eq!(a.checked_mul(&b), Ok(&a * &b));
+    assert_eq!(a.checked_div(&b), Ok(&a / &b));
+    assert_eq!(&b.try_inverse().unwrap() * &b, FpElem::new(1, 65537));
+
+    let other = FpElem::new(5u64, 65539);
+    assert_eq!(a.checked_add(&other), Err(ArithmeticError::ModulusMismatch));
+    assert_eq!(a.checked_sub(&other), Err(ArithmeticError::ModulusMismatch));
+    assert_eq!(a.checked_mul(&other), Err(ArithmeticError::ModulusMismatch));
+    assert_eq!(a.checked_div(&other), Err(ArithmeticError::ModulusMismatch));
+
+    // 0 never inverts, and mod 15 nothing sharing a factor with it does either
+    let zero = FpElem::new(0u64, 65537);
+    assert_eq!(zero.try_inverse(), Err(ArithmeticError::NotInvertible));
+    assert_eq!(a.checked_div(&zero), Err(ArithmeticError::NotInvertible));
+    for (x, invertible) in [(1u64, true), (2, true), (3, false), (5, false), (7, true), (10, false)] {
+        assert_eq!(FpElem::new(x, 15).try_inverse().is_ok(), invertible, "{}", x);
+    }
+}
@@ -0,0 +1,106 @@
+use rustecdsg::{CurveDomain, CurveError, ECPoint};
+extern crate primitive_types;
+use primitive_types::{U256, U512};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+// y^2 = x^3 + 5 over F_223 has 229 points, and lambda = 94 goes with beta = 39
+fn toy_domain() -> CurveDomain<u64> {
+    let g = ECPoint::new(3u64, 60u64, 0u64, 5u64, 223u64);
+    CurveDomain::new("toy", g, 229u64, 1).unwrap()
+}
+
+fn random_below(n: U512, rng: &mut StdRng) -> U512 {
+    let words: [u64; 8] = rng.gen();
+    U512(words) % n
+}
+
+#[test]
+fn glv_toy_curve_every_scalar() {
+    let domain = toy_domain().with_endomorphism(39, 94).unwrap();
+    let endomorphism = *domain.endomorphism().unwrap();
+    assert_eq!((endomorphism.beta, endomorphism.lambda), (39, 94));
+    let g = domain.generator();
+    for k in 0..229u64 {
+        let ((k1, neg1), (k2, neg2)) = endomorphism.decompose(k).unwrap();
+        // k1 + k2 lambda = k mod n, with both halves around sqrt(229)
+        let signed = |x: u64, neg: bool| if neg { 229 - x % 229 } else { x };
+        assert_eq!((signed(k1, neg1) + signed(k2, neg2) * 94) % 229, k);
+        assert!(k1 < 32 && k2 < 32, "{} -> {} {}", k, k1, k2);
+        assert_eq!(domain.mul(&g, k), g * k);
+    }
+    assert_eq!(endomorphism.apply(&g), g * 94);
+}
+
+#[test]
+fn glv_named_curves() {
+    let mut rng = StdRng::seed_from_u64(22);
+    for domain in [
+        CurveDomain::<U512>::secp256k1(),
+        CurveDomain::<U512>::secp224k1(),
+    ] {
+        let endomorphism = *domain.endomorphism().unwrap();
+        // the built in constants pass the same checks as user supplied ones
+        assert_eq!(
+            domain.clone().with_endomorphism(endomorphism.beta, endomorphism.lambda),
+            Ok(domain.clone())
+        );
+        let n = domain.n();
+        let half = U512::one() << (domain.n().bits() / 2 + 2);
+        let g = domain.generator();
+        let p = g * random_below(n, &mut rng);
+        for _ in 0..3 {
+            let k = random_below(n, &mut rng);
+            let ((k1, neg1), (k2, neg2)) = endomorphism.decompose(k).unwrap();
+            assert!(k1 < half && k2 < half);
+            let signed = |x: U512, neg: bool| if neg { n - x } else { x };
+            let lambda = endomorphism.lambda;
+            assert_eq!((signed(k1, neg1) + signed(k2, neg2) * lambda % n) % n, k);
+            assert_eq!(domain.mul(&p, k), p * k);
+        }
+        let (u, v) = (random_below(n, &mut rng), random_below(n, &mut rng));
+        assert_eq!(domain.multi_mul(&[(g, u), (p, v)]), g * u + p * v);
+        assert_eq!(domain.mul(&g, n).position, None);
+        assert_eq!(domain.mul(&g, n - U512::one()), -g);
+    }
+}
+
+#[test]
+fn glv_falls_back_when_type_too_narrow() {
+    // secp224k1 fits a U256 but the products in the decomposition don't
+    let domain = CurveDomain::<U256>::secp224k1();
+    let endomorphism = *domain.endomorphism().unwrap();
+    assert_eq!(endomorphism.decompose(U256::from(12345)), None);
+    let g = domain.generator();
+    assert_eq!(domain.mul(&g, U256::from(12345)), g * U256::from(12345));
+}
+
+#[test]
+fn glv_rejects_bad_endomorphisms() {
+    let domain = toy_domain();
+    // the other cube root of 1 mod p is a different map, (x, y) -> (183 x, y) is 134 G
+    assert_eq!(
+        domain.clone().with_endomorphism(183, 94),
+        Err(CurveError::InvalidEndomorphism)
+    );
+    assert!(domain.clone().with_endomorphism(183, 134).is_ok());
+    // not cube roots of 1 at all
+    assert_eq!(
+        domain.clone().with_endomorphism(1, 1),
+        Err(CurveError::InvalidEndomorphism)
+    );
+    assert_eq!(
+        domain.clone().with_endomorphism(2, 94),
+        Err(CurveError::InvalidEndomorphism)
+    );
+    // a != 0
+    let g = ECPoint::new(5u64, 1u64, 2u64, 2u64, 17u64);
+    let other = CurveDomain::new("toy", g, 19u64, 1).unwrap();
+    assert_eq!(
+        other.with_endomorphism(1, 7),
+        Err(CurveError::InvalidEndomorphism)
+    );
+    // and no endomorphism means multi_mul is plain ECPoint::multi_mul
+    assert_eq!(domain.endomorphism(), None);
+    assert_eq!(domain.mul(&domain.generator(), 100), domain.generator() * 100);
+}
@@ -0,0 +1,46 @@
+use rustecdsg::{bits2int, bits2octets, digest_to_scalar, HashAlgorithm};
+extern crate primitive_types;
+use primitive_types::U512;
+
+#[test]
+fn bits2int_truncates() {
+    // format: (bytes, qlen, expected)
+    let cases: [(&[u8], u64, u64); 5] = [
+        (&[0x12, 0x34], 16, 0x1234),
+        // wider than q - keep the leftmost qlen bits
+        (&[0x12, 0x34], 12, 0x123),
+        (&[0xff, 0xff, 0xff], 9, 0x1ff),
+        // narrower than q - nothing to drop
+        (&[0x12, 0x34], 20, 0x1234),
+        (&[], 8, 0),
+    ];
+    for (bytes, qlen, expected) in cases.iter() {
+        assert_eq!(bits2int::<u64>(bytes, *qlen), *expected);
+    }
+}
+
+#[test]
+fn bits2octets_reduces() {
+    // q = 0x1f1 is 9 bits, so 0xffff -> 0x1ff -> 0x1ff - 0x1f1 = 0x0e, written as 2 bytes
+    assert_eq!(bits2octets(&[0xff, 0xff], 0x1f1u64), vec![0x00, 0x0e]);
+    assert_eq!(bits2octets(&[0x01], 0x1f1u64), vec![0x00, 0x01]);
+}
+
+#[test]
+fn digest_to_scalar_p256() {
+    let n = U512::from("0xffffffff00000000ffffffffffffffffbce6faada7179e84f3b9cac2fc632551");
+    // SHA-256 is exactly as wide as n so is just reduced
+    let digest = HashAlgorithm::Sha256.digest(b"sample");
+    assert_eq!(digest.len(), HashAlgorithm::Sha256.output_size());
+    assert_eq!(
+        digest_to_scalar(&digest, n).number,
+        U512::from_big_endian(&digest) % n
+    );
+    // SHA-512 keeps only its first 256 bits
+    let digest = HashAlgorithm::Sha512.digest(b"sample");
+    assert_eq!(digest.len(), HashAlgorithm::Sha512.output_size());
+    assert_eq!(
+        digest_to_scalar(&digest, n).number,
+        U512::from("0x39a5e04aaff7455d9850c605364f514c11324ce64016960d23d5dc57d3ffd8f4")
+    );
+}
@@ -0,0 +1,82 @@
+use rustecdsg::{CurveDomain, ECPoint, FpElem, JacobianPoint};
+extern crate primitive_types;
+use primitive_types::U512;
+
+fn toy_points() -> Vec<ECPoint<FpElem<u64>>> {
+    // y^2 = x^3 + 2x + 2 over F_17, all 19 points as multiples of (5, 1)
+    let g: ECPoint<FpElem<u64>> = ECPoint::new(5u64, 1u64, 2u64, 2u64, 17u64);
+    let mut points = vec![ECPoint::new_infinity(2u64, 2u64, 17u64)];
+    for _ in 0..19 {
+        let last = *points.last().unwrap();
+        points.push(last + g);
+    }
+    points
+}
+
+#[test]
+fn jacobian_matches_affine() {
+    let points = toy_points();
+    for p in points.iter() {
+        let jp = JacobianPoint::from_affine(p);
+        assert_eq!(jp.to_affine(), *p);
+        assert_eq!(jp.double().to_affine(), *p + *p);
+        for q in points.iter() {
+            let jq = JacobianPoint::from_affine(q);
+            assert_eq!((jp + jq).to_affine(), *p + *q);
+            // and with both Z coordinates something other than 1
+            assert_eq!(
+                (jp.double() + jq.double()).to_affine(),
+                (*p + *p) + (*q + *q)
+            );
+        }
+    }
+}
+
+#[test]
+fn jacobian_eq_ignores_scaling() {
+    let points = toy_points();
+    let doubled = JacobianPoint::from_affine(&points[3]).double();
+    assert_eq!(doubled, JacobianPoint::from_affine(&points[6]));
+    assert_ne!(doubled, JacobianPoint::from_affine(&points[7]));
+    let inf = JacobianPoint::from_affine(&points[0]);
+    assert!(inf.is_infinity());
+    assert_eq!(inf, JacobianPoint::from_affine(&points[19]));
+    assert_ne!(inf, doubled);
+}
+
+#[test]
+fn jacobian_neg() {
+    let points = toy_points();
+    for (k, p) in points.iter().enumerate() {
+        let jp = JacobianPoint::from_affine(p).double();
+        assert!((jp + -jp).is_infinity());
+        assert_eq!((-jp).to_affine(), points[(19 - 2 * k % 19) % 19]);
+    }
+}
+
+#[test]
+fn jacobian_double_two_torsion() {
+    // (6, 0) on y^2 = x^3 + 7 over F_223 has order 2
+    let p: ECPoint<FpElem<u64>> = ECPoint::new(6u64, 0u64, 0u64, 7u64, 223u64);
+    let jp = JacobianPoint::from_affine(&p);
+    assert!(jp.double().is_infinity());
+    assert!((jp + jp).is_infinity());
+    assert_eq!((p * 2).position, None);
+    assert_eq!(p * 3, p);
+}
+
+#[test]
+fn scalar_mul_secp256k1() {
+    let g = CurveDomain::<U512>::secp256k1().generator();
+    let k = U512::from("0xaa5e28d6a97a2479a65527f7290311a3624d4cc0fa1578598ee3c2613bf99522");
+    let x = U512::from("0x34f9460f0e4f08393d192b3c5133a6ba099aa0ad9fd54ebccfacdfa239ff49c6");
+    let y = U512::from("0x0b71ea9bd730fd8923f6d25a7a91e7dd7728a960686cb5a901bb419e0f2ca232");
+    let expected = ECPoint::new(
+        x,
+        y,
+        U512::from(0),
+        U512::from(7),
+        U512::from("0xfffffffffffffffffffffffffffffffffffffffffffffffffffffffefffffc2f"),
+    );
+    assert_eq!(g * k, expected);
+}
@@ -0,0 +1,221 @@
+use rustecdsg::{
+    generic_uint_from_be_bytes, generic_uint_to_be_bytes, ArithmeticError, CurveDomain, ECPoint,
+    FieldElement, FpElem, GenericUInt, MontFpElem, MontgomeryParams, Pow,
+};
+use std::fmt::Debug;
+extern crate primitive_types;
+use primitive_types::{U256, U512};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+// every operator on random elements against FpElem, which does it the slow way. prime is prime
+fn check_against_fpelem<T: GenericUInt + Debug>(prime: T, seed: u64) {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let params = MontgomeryParams::new(prime);
+    for _ in 0..20 {
+        let (x, y) = (
+            FpElem::random(prime, &mut rng),
+            FpElem::random_nonzero(prime, &mut rng),
+        );
+        let (mx, my) = (
+            MontFpElem::with_params(x.number, &params),
+            MontFpElem::with_params(y.number, &params),
+        );
+        assert_eq!(mx.to_fp(), x);
+        assert_eq!((mx + my).to_fp(), x + y);
+        assert_eq!((mx - my).to_fp(), x - y);
+        assert_eq!((my - mx).to_fp(), y - x);
+        assert_eq!((mx * my).to_fp(), x * y);
+        assert_eq!((mx / my).to_fp(), x / y);
+        assert_eq!((-mx).to_fp(), -x);
+        assert_eq!((mx * y.number).to_fp(), x * y.number);
+        // FpElem's pow is too slow to compare full size exponents with, so those go by Fermat
+        assert_eq!(mx.pow(T::from(200)).to_fp(), x.pow(T::from(200)));
+        assert_eq!(my.pow(prime - T::from(1)).number(), T::from(1));
+    }
+}
+
+#[test]
+fn mont_matches_fpelem() {
+    check_against_fpelem(65537u64, 1);
+    // 2^61 - 1 and the biggest prime below 2^64 take two 32 bit words
+    check_against_fpelem((1u64 << 61) - 1, 2);
+    check_against_fpelem(18446744073709551557u64, 3);
+    check_against_fpelem(u128::MAX >> 1, 4);
+    for p in [
+        // secp256k1, P-256 and 2^255 - 19
+        "0xfffffffffffffffffffffffffffffffffffffffffffffffffffffffefffffc2f",
+        "0xffffffff00000001000000000000000000000000ffffffffffffffffffffffff",
+        "0x7fffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffed",
+    ] {
+        check_against_fpelem(U256::from(p), 5);
+        check_against_fpelem(U512::from(p), 6);
+    }
+}
+
+#[test]
+fn mont_add_sub_edges() {
+    // every pair from the ends of the field, where the carries and borrows come out - in a U256
+    // secp256k1's p leaves no room for a + b
+    fn check<T: GenericUInt + Debug>(prime: T) {
+        let params = MontgomeryParams::new(prime);
+        let (zero, one) = (T::from(0), T::from(1));
+        let ends = [zero, one, T::from(2), prime - T::from(2), prime - one];
+        for x in ends {
+            for y in ends {
+                let (fx, fy) = (FpElem::new(x, prime), FpElem::new(y, prime));
+                let (mx, my) = (
+                    MontFpElem::with_params(x, &params),
+                    MontFpElem::with_params(y, &params),
+                );
+                assert_eq!((mx + my).to_fp(), fx + fy, "{:?} + {:?}", x, y);
+                assert_eq!((mx - my).to_fp(), fx - fy, "{:?} - {:?}", x, y);
+                assert_eq!(mx.select(&my, zero), mx);
+                assert_eq!(mx.select(&my, one), my);
+            }
+        }
+    }
+    check(251u8);
+    check(65537u64);
+    check(18446744073709551557u64);
+    check(U256::from(
+        "0xfffffffffffffffffffffffffffffffffffffffffffffffffffffffefffffc2f",
+    ));
+}
+
+#[test]
+// the borrowed forms on purpose
+#[allow(clippy::op_ref)]
+fn mont_operator_forms() {
+    let params = MontgomeryParams::new(65537u64);
+    let (a, b) = (
+        MontFpElem::with_params(9192, &params),
+        MontFpElem::with_params(127712, &params),
+    );
+    // another copy of the same params is the same field
+    let copy = params.clone();
+    assert_eq!(MontFpElem::with_params(9192, &copy), a);
+    assert_eq!(a.number(), 9192);
+    assert_eq!(b.number(), 127712 % 65537);
+    assert_eq!(a.prime(), 65537);
+    assert_eq!(a + b, &a + &b);
+    assert_eq!(&a - b, &a - &b);
+    assert_eq!(a * &b, &a * &b);
+
+    let mut c = a;
+    c += b;
+    c -= &b;
+    assert_eq!(c, a);
+    c *= b;
+    c /= &b;
+    assert_eq!(c, a);
+    c *= 2;
+    assert_eq!(c, a + a);
+    assert_eq!(-&a + a, MontFpElem::with_params(0, &params));
+    assert_eq!(MontFpElem::try_sum([a, b]), Some(a + b));
+    assert_eq!([a, b].iter().sum::<Option<_>>(), Some(a + b));
+    assert_eq!(format!("{}", a), "9192");
+
+    // both ways round
+    let x = FpElem::new(9192u64, 65537);
+    assert_eq!(MontFpElem::with_params(x.number, &params), a);
+    assert_eq!(FpElem::from(a), x);
+}
+
+#[test]
+fn mont_element_size() {
+    // the params are shared, an element is only its value and a reference
+    let size = std::mem::size_of::<MontFpElem<U256>>();
+    assert_eq!(size, std::mem::size_of::<(U256, &MontgomeryParams<U256>)>());
+}
+
+#[test]
+fn mont_checked() {
+    let params = MontgomeryParams::new(65537u64);
+    let (a, b) = (
+        MontFpElem::with_params(9192, &params),
+        MontFpElem::with_params(127712, &params),
+    );
+    assert_eq!(a.checked_add(&b), Ok(a + b));
+    assert_eq!(a.checked_div(&b), Ok(a / b));
+    assert_eq!((b.try_inverse().unwrap() * b).number(), 1);
+    let other_params = MontgomeryParams::new(65539u64);
+    let other = MontFpElem::with_params(5, &other_params);
+    assert_eq!(a.checked_mul(&other), Err(ArithmeticError::ModulusMismatch));
+    assert_eq!(a.checked_sub(&other), Err(ArithmeticError::ModulusMismatch));
+    let zero = MontFpElem::with_params(0, &params);
+    assert_eq!(zero.try_inverse(), Err(ArithmeticError::NotInvertible));
+    assert_eq!(a.checked_div(&zero), Err(ArithmeticError::NotInvertible));
+    let fifteen = MontgomeryParams::new(15u64);
+    for (x, invertible) in [(1u64, true), (2, true), (3, false), (5, false), (7, true)] {
+        assert_eq!(
+            MontFpElem::with_params(x, &fifteen).try_inverse().is_ok(),
+            invertible,
+            "{}",
+            x
+        );
+    }
+}
+
+#[test]
+#[should_panic(expected = "Montgomery form needs an odd modulus")]
+fn mont_even_modulus() {
+    MontgomeryParams::new(1u64 << 32);
+}
+
+#[test]
+fn mont_ecpoint_small_curve() {
+    // every multiple of a generator of the 19 point curve y^2 = x^3 + 2x + 2 over F_17
+    let g: ECPoint<FpElem<u64>> = ECPoint::new(5u64, 1u64, 2u64, 2u64, 17u64);
+    let params = MontgomeryParams::new(17u64);
+    let mg = g.to_montgomery(&params);
+    assert!(mg.is_on_curve());
+    let mut expected = mg;
+    for k in 1..40u64 {
+        assert_eq!(mg * k, expected, "{}", k);
+        assert_eq!(ECPoint::<FpElem<u64>>::from(mg * k), g * k);
+        expected += mg;
+    }
+    assert_eq!(mg - mg, mg * 19);
+    assert_eq!(ECPoint::try_sum([mg, mg, mg]), Some(mg * 3));
+    assert_eq!(ECPoint::multi_mul(&[(mg, 5), (mg * 2, 7)]), mg * 19u64);
+}
+
+#[test]
+fn mont_ecpoint_is_in_subgroup() {
+    // y^2 = x^3 + 7 over F_223 has 252 = 21 * 12 points, (47, 71) has order 21 and (6, 0) order 2
+    let params = MontgomeryParams::new(223u64);
+    let curve = |x: u64, y: u64| ECPoint::new(x, y, 0u64, 7u64, 223u64).to_montgomery(&params);
+    assert!(curve(47, 71).is_in_subgroup(21, 12));
+    assert!(!curve(6, 0).is_in_subgroup(21, 12));
+    assert!(!curve(47, 70).is_in_subgroup(21, 12));
+}
+
+#[test]
+fn mont_ecpoint_secp256k1() {
+    // in a U256, which is too narrow for FpElem's multiply and reduce shortcut - that's where this
+    // pays off - checked against FpElem in a U512
+    let domain = CurveDomain::<U512>::secp256k1();
+    let narrow = |x: U512| generic_uint_from_be_bytes::<U256>(&generic_uint_to_be_bytes(x, 32));
+    let (x, y) = domain.generator().position.unwrap();
+    let params = MontgomeryParams::new(narrow(domain.p));
+    let g = ECPoint::new(narrow(x.number), narrow(y.number), 0, 7, narrow(domain.p))
+        .to_montgomery(&params);
+    assert!(g.is_on_curve());
+    let mut rng = StdRng::seed_from_u64(25);
+    for _ in 0..2 {
+        let k = FpElem::random_nonzero(domain.n(), &mut rng).number;
+        let point = g * narrow(k);
+        assert!(point.is_on_curve());
+        let (x, y) = point.position.unwrap();
+        let expected = (domain.generator() * k).position.unwrap();
+        assert_eq!(
+            (U512::from(x.number()), U512::from(y.number())),
+            (expected.0.number, expected.1.number)
+        );
+    }
+    assert_eq!((g * narrow(domain.n())).position, None);
+    let small = MontgomeryParams::new(U256::from(17));
+    let other = ECPoint::new(5u64, 1u64, 2u64, 2u64, 17u64).to_montgomery(&small);
+    assert_eq!(g.checked_add(&other), Err(ArithmeticError::CurveMismatch));
+}
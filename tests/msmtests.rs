@@ -0,0 +1,93 @@
+use rustecdsg::{CurveDomain, ECPoint, FpElem};
+extern crate primitive_types;
+use primitive_types::U512;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+// y^2 = x^3 + 5 over F_1000003, (1, 586770) has prime order 999007
+fn medium_generator() -> ECPoint<FpElem<u64>> {
+    ECPoint::new(1u64, 586770u64, 0u64, 5u64, 1000003u64)
+}
+
+fn one_at_a_time(terms: &[(ECPoint<FpElem<u64>>, u64)]) -> ECPoint<FpElem<u64>> {
+    terms.iter().fold(
+        ECPoint::new_infinity(0u64, 5u64, 1000003u64),
+        |sum, (p, k)| sum + *p * *k,
+    )
+}
+
+// random multiples of g with random scalars, plus zero scalars, infinity and a point with its
+// negative thrown in
+fn random_terms(count: usize, rng: &mut StdRng) -> Vec<(ECPoint<FpElem<u64>>, u64)> {
+    let g = medium_generator();
+    let mut terms: Vec<(ECPoint<FpElem<u64>>, u64)> = (0..count)
+        .map(|_| {
+            (
+                g * rng.gen_range(1..999007u64),
+                rng.gen_range(0..1u64 << 40),
+            )
+        })
+        .collect();
+    terms[0].1 = 0;
+    terms[1].0 = ECPoint::new_infinity(0u64, 5u64, 1000003u64);
+    terms[2] = (-terms[3].0, terms[3].1);
+    terms
+}
+
+#[test]
+fn multi_mul_two_terms() {
+    let g = medium_generator();
+    let h = g * 12345;
+    let mut rng = StdRng::seed_from_u64(21);
+    for _ in 0..50 {
+        let (a, b) = (rng.gen_range(0..1u64 << 40), rng.gen_range(0..1u64 << 20));
+        let terms = [(g, a), (h, b)];
+        assert_eq!(ECPoint::multi_mul(&terms), one_at_a_time(&terms));
+    }
+    assert_eq!(ECPoint::multi_mul(&[(g, 0), (h, 0)]).position, None);
+    assert_eq!(ECPoint::multi_mul(&[(g, 7), (-g, 7)]).position, None);
+    assert_eq!(ECPoint::multi_mul(&[(g, 99)]), g * 99);
+}
+
+#[test]
+fn multi_mul_few_and_many_terms() {
+    // a handful goes through Straus, a few hundred through Pippenger
+    let mut rng = StdRng::seed_from_u64(22);
+    for count in [4, 5, 16, 300] {
+        let terms = random_terms(count, &mut rng);
+        assert_eq!(
+            ECPoint::multi_mul(&terms),
+            one_at_a_time(&terms),
+            "{}",
+            count
+        );
+    }
+}
+
+#[test]
+fn multi_mul_secp256k1() {
+    // a G + b (2G) + c (3G) = (a + 2b + 3c) G
+    let domain = CurveDomain::<U512>::secp256k1();
+    let (g, n) = (domain.generator(), domain.n());
+    let a = U512::from("0xaa5e28d6a97a2479a65527f7290311a3624d4cc0fa1578598ee3c2613bf99522");
+    let b = U512::from("0x7e2b897b8cebc6361663ad410835639826d590f393d90a9538881735256dfae3");
+    let c = U512::from("0x6c7a3b8e56a0c1f0ec5c4d3b2a8f6e3b4a9b8c7d6e5f4a3b2c1d0e9f8a7b6c5d");
+    let (g2, g3) = (g * U512::from(2), g * U512::from(3));
+    let expected = g * ((a + b * U512::from(2) + c * U512::from(3)) % n);
+    assert_eq!(ECPoint::multi_mul(&[(g, a), (g2, b), (g3, c)]), expected);
+    let expected = g * ((a + b * U512::from(2)) % n);
+    assert_eq!(ECPoint::multi_mul(&[(g, a), (g2, b)]), expected);
+}
+
+#[test]
+#[should_panic(expected = "multi_mul needs at least one term")]
+fn multi_mul_no_terms() {
+    ECPoint::<FpElem<u64>>::multi_mul(&[]);
+}
+
+#[test]
+#[should_panic(expected = "Curves must be the same")]
+fn multi_mul_mixed_curves() {
+    let g: ECPoint<FpElem<u64>> = ECPoint::new(5u64, 1u64, 2u64, 2u64, 17u64);
+    ECPoint::multi_mul(&[(g, 1), (medium_generator(), 1), (g, 2)]);
+}
@@ -0,0 +1,178 @@
+use rustecdsg::{CurveDomain, ECPoint, FpElem, MontgomeryParams, ProjectivePoint};
+extern crate primitive_types;
+use primitive_types::U512;
+
+fn inverse(x: u64, p: u64) -> u64 {
+    // Fermat, p is prime
+    let mut out = 1;
+    for _ in 0..p - 2 {
+        out = out * x % p;
+    }
+    out
+}
+
+// textbook chord and tangent on plain integers, with every special case spelled out, to check
+// the complete formulas against
+fn reference_add(
+    lhs: Option<(u64, u64)>,
+    rhs: Option<(u64, u64)>,
+    a: u64,
+    p: u64,
+) -> Option<(u64, u64)> {
+    let ((x1, y1), (x2, y2)) = match (lhs, rhs) {
+        (None, _) => return rhs,
+        (_, None) => return lhs,
+        (Some(l), Some(r)) => (l, r),
+    };
+    let s = if x1 != x2 {
+        (y2 + p - y1) % p * inverse((x2 + p - x1) % p, p) % p
+    } else if y1 == y2 && y1 != 0 {
+        (3 * x1 % p * x1 + a) % p * inverse(2 * y1 % p, p) % p
+    } else {
+        return None;
+    };
+    let x3 = (s * s % p + 2 * p - x1 - x2) % p;
+    let y3 = (s * ((x1 + p - x3) % p) % p + p - y1) % p;
+    Some((x3, y3))
+}
+
+// every point on y^2 = x^3 + ax + b over F_p, infinity first
+fn all_points(a: u64, b: u64, p: u64) -> Vec<ECPoint<FpElem<u64>>> {
+    let mut points = vec![ECPoint::new_infinity(a, b, p)];
+    for x in 0..p {
+        for y in 0..p {
+            if y * y % p == (x * x % p * x + a * x + b) % p {
+                points.push(ECPoint::new(x, y, a, b, p));
+            }
+        }
+    }
+    points
+}
+
+fn position(point: &ECPoint<FpElem<u64>>) -> Option<(u64, u64)> {
+    point.position.map(|(x, y)| (x.number, y.number))
+}
+
+// affine Add against the reference for all pairs, which is fine on any curve
+fn check_affine(a: u64, b: u64, p: u64) {
+    let points = all_points(a, b, p);
+    for lhs in points.iter() {
+        for rhs in points.iter() {
+            let expected = reference_add(position(lhs), position(rhs), a, p);
+            assert_eq!(position(&(*lhs + *rhs)), expected, "{:?} + {:?}", lhs, rhs);
+        }
+    }
+}
+
+// all pairs, once with Z = 1 and once with both points doubled up to Z != 1
+// the curve's order has to be odd for the formulas to be complete
+fn check_projective(a: u64, b: u64, p: u64) {
+    let points = all_points(a, b, p);
+    assert_eq!(points.len() % 2, 1);
+    for lhs in points.iter() {
+        let plhs = ProjectivePoint::from_affine(lhs);
+        assert_eq!(plhs.to_affine(), *lhs);
+        for rhs in points.iter() {
+            let prhs = ProjectivePoint::from_affine(rhs);
+            let expected = reference_add(position(lhs), position(rhs), a, p);
+            assert_eq!(
+                position(&(plhs + prhs).to_affine()),
+                expected,
+                "{:?} + {:?}",
+                lhs,
+                rhs
+            );
+            let (lhs2, rhs2) = (
+                reference_add(position(lhs), position(lhs), a, p),
+                reference_add(position(rhs), position(rhs), a, p),
+            );
+            assert_eq!(
+                position(&(plhs.double() + prhs.double()).to_affine()),
+                reference_add(lhs2, rhs2, a, p)
+            );
+        }
+    }
+}
+
+#[test]
+fn projective_complete_a_zero() {
+    // y^2 = x^3 + 5 over F_223, 229 points
+    check_projective(0, 5, 223);
+}
+
+#[test]
+fn projective_complete_a_minus_three() {
+    // y^2 = x^3 - 3x + 5 over F_107, 101 points
+    check_projective(104, 5, 107);
+}
+
+#[test]
+fn projective_complete_any_a() {
+    // y^2 = x^3 + 2x + 2 over F_17, 19 points
+    check_projective(2, 2, 17);
+}
+
+#[test]
+fn affine_add_all_pairs() {
+    // each of these has a point of order 2: (6, 0), (3, 0) and (0, 0)
+    check_affine(0, 7, 223);
+    check_affine(20, 5, 23);
+    check_affine(1, 0, 23);
+    check_affine(2, 2, 17);
+}
+
+#[test]
+fn affine_double_two_torsion() {
+    // this used to divide by 2y = 0 and come out as a point rather than infinity
+    let p: ECPoint<FpElem<u64>> = ECPoint::new(6u64, 0u64, 0u64, 7u64, 223u64);
+    assert_eq!((p + p).position, None);
+    assert_eq!((p + p) + p, p);
+}
+
+#[test]
+fn projective_eq_ignores_scaling() {
+    let points = all_points(0, 5, 223);
+    let p = ProjectivePoint::from_affine(&points[5]);
+    assert_eq!(
+        p.double(),
+        ProjectivePoint::from_affine(&(points[5] + points[5]))
+    );
+    assert_ne!(p.double(), p);
+    let inf = ProjectivePoint::from_affine(&points[0]);
+    assert!(inf.is_infinity());
+    assert_eq!(inf + inf, inf);
+    assert_eq!(p + inf, p);
+}
+
+#[test]
+fn projective_real_curves() {
+    // 2G + G = 3G on secp256k1 (a = 0) and P-256 (a = -3)
+    for domain in [
+        CurveDomain::<U512>::secp256k1(),
+        CurveDomain::<U512>::p256(),
+    ] {
+        let g = ProjectivePoint::from_affine(&domain.generator());
+        let three = domain.generator() * U512::from(3);
+        assert_eq!((g.double() + g).to_affine(), three);
+        assert_eq!((g + g.double()).to_affine(), three);
+    }
+}
+
+#[test]
+fn projective_montgomery() {
+    // the same sums with the coordinates in Montgomery form, on each of the three formulas
+    for (a, b, p) in [(0u64, 5u64, 223u64), (104, 5, 107), (2, 2, 17)] {
+        let params = MontgomeryParams::new(p);
+        let points = all_points(a, b, p);
+        for lhs in points.iter() {
+            let plhs = ProjectivePoint::from_affine(&lhs.to_montgomery(&params));
+            for rhs in points.iter().step_by(3) {
+                let prhs = ProjectivePoint::from_affine(&rhs.to_montgomery(&params));
+                let sum = ECPoint::<FpElem<u64>>::from((plhs + prhs).to_affine());
+                assert_eq!(sum, *lhs + *rhs, "{:?} + {:?}", lhs, rhs);
+                let doubled = ECPoint::<FpElem<u64>>::from(plhs.double().to_affine());
+                assert_eq!(doubled, *lhs + *lhs);
+            }
+        }
+    }
+}
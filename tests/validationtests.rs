@@ -0,0 +1,113 @@
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use rustecdsg::{Check, CurveDomain, ECPoint, EmbeddingDegree, FpElem};
+extern crate primitive_types;
+use primitive_types::U512;
+
+fn domain(g: (u64, u64), a: u64, b: u64, p: u64, n: u64, cofactor: u64) -> CurveDomain<u64> {
+    let g: ECPoint<FpElem<u64>> = ECPoint::new(g.0, g.1, a, b, p);
+    CurveDomain::new("test", g, n, cofactor).unwrap()
+}
+
+#[test]
+fn validate_secp256k1() {
+    let report = CurveDomain::<U512>::secp256k1().validate_with_rng(&mut StdRng::seed_from_u64(0));
+    let n = CurveDomain::<U512>::secp256k1().n();
+    assert_eq!(report.curve_order, Some(n));
+    assert_eq!(report.embedding_degree, EmbeddingDegree::AtLeast(100));
+    // the twist order is 3^2 * 13^2 * 3319 * 22639 * a 220 bit prime
+    assert_eq!(report.twist_largest_prime_bits, Some(220));
+    assert!(report.is_safe(), "{}", report);
+}
+
+#[test]
+fn validate_unfactored_twist() {
+    // brainpoolP160r1's twist order has a large composite part trial division can't split
+    let report =
+        CurveDomain::<U512>::brainpool_p160r1().validate_with_rng(&mut StdRng::seed_from_u64(0));
+    assert_eq!(report.cofactor, Check::Pass);
+    assert_eq!(report.mov, Check::Pass);
+    assert_eq!(report.twist_largest_prime_bits, None);
+    assert_eq!(report.twist, Check::Unknown);
+    assert!(!report.is_safe());
+}
+
+#[test]
+fn validate_toy_curves() {
+    let mut rng = StdRng::seed_from_u64(1);
+    // y^2 = x^3 + 2x + 2 over F_17 has 19 points, twist order 2 * 18 - 19 = 17
+    let report = domain((5, 1), 2, 2, 17, 19, 1).validate_with_rng(&mut rng);
+    assert_eq!(report.discriminant, Check::Pass);
+    assert_eq!(report.p_prime, Check::Pass);
+    assert_eq!(report.n_prime, Check::Pass);
+    assert_eq!(report.generator_order, Check::Pass);
+    assert_eq!(report.curve_order, Some(19));
+    assert_eq!(report.cofactor, Check::Pass);
+    // 17 has order 9 mod 19
+    assert_eq!(report.embedding_degree, EmbeddingDegree::Exactly(9));
+    assert_eq!(report.mov, Check::Fail);
+    assert_eq!(report.anomalous, Check::Pass);
+    assert_eq!(report.twist_order, Some(17));
+    assert_eq!(report.twist_largest_prime_bits, Some(5));
+    assert_eq!(report.twist, Check::Fail);
+    assert!(!report.is_safe());
+
+    // same curve with the wrong cofactor, and a generator that isn't of order n
+    let report = domain((5, 1), 2, 2, 17, 19, 2).validate_with_rng(&mut rng);
+    assert_eq!(report.cofactor, Check::Fail);
+    let report = domain((5, 1), 2, 2, 17, 23, 1).validate_with_rng(&mut rng);
+    assert_eq!(report.generator_order, Check::Fail);
+    assert_eq!(report.curve_order, Some(19));
+
+    // y^2 = x^3 + 7 over F_223 has 252 = 12 * 21 points, 21 isn't prime
+    let report = domain((47, 71), 0, 7, 223, 21, 12).validate_with_rng(&mut rng);
+    assert_eq!(report.n_prime, Check::Fail);
+    assert_eq!(report.generator_order, Check::Pass);
+    assert_eq!(report.cofactor, Check::Pass);
+
+    // y^2 = x^3 has a cusp at (0, 0)
+    let report = domain((1, 1), 0, 0, 17, 17, 1).validate_with_rng(&mut rng);
+    assert_eq!(report.discriminant, Check::Fail);
+    // and y^2 = x^3 + 2x + 2 mod 15 isn't over a field at all
+    let report = domain((1, 0), 2, 12, 15, 19, 1).validate_with_rng(&mut rng);
+    assert_eq!(report.p_prime, Check::Fail);
+}
+
+#[test]
+fn validate_anomalous_curve() {
+    // y^2 = x^3 + x + 32 over F_101 has exactly 101 points
+    let report =
+        domain((4, 10), 1, 32, 101, 101, 1).validate_with_rng(&mut StdRng::seed_from_u64(2));
+    assert_eq!(report.curve_order, Some(101));
+    assert_eq!(report.cofactor, Check::Pass);
+    assert_eq!(report.anomalous, Check::Fail);
+}
+
+#[test]
+fn validate_supersingular_curve() {
+    // y^2 = x^3 + x over F_p with p = 3 mod 4 has p + 1 points and embedding degree 2, and as
+    // n > 4 sqrt(p) the curve order comes from the Hasse bound rather than counting
+    let p = 1000171;
+    let report = domain((384358, 923013), 1, 0, p, 250043, 4)
+        .validate_with_rng(&mut StdRng::seed_from_u64(3));
+    assert_eq!(report.curve_order, Some(p + 1));
+    assert_eq!(report.cofactor, Check::Pass);
+    assert_eq!(report.embedding_degree, EmbeddingDegree::Exactly(2));
+    assert_eq!(report.mov, Check::Fail);
+    assert_eq!(report.twist_order, Some(p + 1));
+    assert_eq!(report.twist_largest_prime_bits, Some(18));
+}
+
+#[test]
+fn validation_report_display() {
+    let report = domain((5, 1), 2, 2, 17, 19, 1).validate_with_rng(&mut StdRng::seed_from_u64(4));
+    let printed = format!("{}", report);
+    let lines: Vec<&str> = printed.lines().collect();
+    assert_eq!(lines.len(), 10);
+    assert_eq!(lines[0], "test");
+    assert!(lines[1].contains("discriminant") && lines[1].contains("pass"));
+    assert!(lines[5].contains("cofactor") && lines[5].contains("#E = 19"));
+    assert!(lines[6].contains("FAIL") && lines[6].contains("embedding degree 9"));
+    assert!(lines[8].contains("largest twist prime factor 5 bits"));
+    assert_eq!(lines[9], "  safe: no");
+}